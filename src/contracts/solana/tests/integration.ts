@@ -181,7 +181,7 @@ describe("🧞‍♂️ DeGenie Token Creator - Integration Tests", () => {
       const solAmount = new anchor.BN(0.1 * LAMPORTS_PER_SOL); // 0.1 SOL
 
       const tx = await program.methods
-        .buyTokens(solAmount)
+        .buyTokens(solAmount, new anchor.BN(0), null, null) // no slippage bound, deadline or presale proof
         .accounts({
           bondingCurve,
           mint: mint.publicKey,
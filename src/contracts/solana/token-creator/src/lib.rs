@@ -5,28 +5,112 @@ use anchor_spl::{
         create_metadata_accounts_v3, mpl_token_metadata::types::DataV2, CreateMetadataAccountsV3,
         Metadata, mpl_token_metadata::types::Creator,
     },
-    token::{mint_to, burn, transfer, Mint, MintTo, Burn, Transfer, Token, TokenAccount, freeze_account, thaw_account, FreezeAccount, ThawAccount},
+    token::{mint_to, burn, transfer, sync_native, Mint, MintTo, Burn, Transfer, SyncNative, Token, TokenAccount, freeze_account, thaw_account, FreezeAccount, ThawAccount},
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+    program_option::COption,
+    pubkey,
 };
-use solana_program::program_option::COption;
 
 declare_id!("DeGenieTokenCreator11111111111111111111111");
 
-// DeGenie Platform Treasury - Replace with your actual wallet
-pub const DEGENIE_PLATFORM_TREASURY: &str = "3yqm9NMVuZckjMpWwVZ4Vjig1spjYfLVP9jgDWybrcCF";
-
-// Fixed graduation threshold - 500 SOL for all tokens
-// Why fixed SOL instead of USD?
-// 1. No oracle dependency = simpler, cheaper, more reliable
-// 2. Traders think in SOL, not USD when trading on Solana
-// 3. Predictable for everyone: "500 SOL = graduation"
-// 4. At SOL=$145, this is ~$72,500 (similar to pump.fun's $69k)
-pub const GRADUATION_THRESHOLD_SOL: u64 = 500;
+// Protocol settings (platform wallet, fees, anti-bot defaults) live in the
+// Config account; the constants below are bounds and fixed protocol values
 pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+// Logarithmic curves may add at most 100x the initial price per unit of ln(supply)
+pub const MAX_LOGARITHMIC_GROWTH_RATE: u64 = 1_000_000;
+// Constant-product curves may hold at most 10x max_supply in virtual token reserves
+pub const MAX_VIRTUAL_RESERVE_RATIO: u64 = 100_000;
+// Maximum number of segments in a multi-stage curve
+pub const MAX_CURVE_STAGES: usize = 5;
+// Curve supplies and prices are denominated in 10^-6 tokens ("curve units")
+// whatever the mint's decimals, so every token is priced on the same scale
+pub const CURVE_DECIMALS: u8 = 6;
+// Limits Config values must respect
+pub const MAX_CREATION_FEE: u64 = LAMPORTS_PER_SOL;
+pub const MAX_TRANSACTION_FEE_BPS: u16 = 1000; // 10%
+pub const MAX_LAUNCH_PROTECTION_PERIOD: i64 = 86_400; // 1 day
+pub const MAX_TRANSACTION_COOLDOWN: u64 = 3600; // 1 hour
+pub const MAX_KEEPER_REWARD: u64 = LAMPORTS_PER_SOL / 10; // 0.1 SOL
+pub const MAX_SNIPER_TAX_BPS: u16 = 9000; // 90% total fee at launch
+pub const MAX_OPENING_AUCTION_DURATION: i64 = 3600; // 1 hour
+// Swap fee charged by graduated pools, left in the pool for liquidity
+pub const POOL_SWAP_FEE_BPS: u64 = 25;
+// External DEXes a curve can migrate into (see MigrationTarget)
+pub const RAYDIUM_AMM_V4_PROGRAM_ID: Pubkey = pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
+pub const RAYDIUM_CPMM_PROGRAM_ID: Pubkey = pubkey!("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C");
+// Lamports held back from the liquidity share to cover a DEX's pool creation
+// fee and rent (0.5 SOL); whatever is left goes to the platform
+pub const DEX_MIGRATION_BUDGET: u64 = 500_000_000;
 
 #[program]
 pub mod degenie_token_creator {
     use super::*;
 
+    /// Create the protocol Config. Only the program's upgrade authority may
+    /// do this; it becomes the admin.
+    pub fn initialize_config(ctx: Context<InitializeConfig>, params: ConfigParams) -> Result<()> {
+        validate_config_params(&params)?;
+
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.bump = ctx.bumps.config;
+        config.paused = false;
+        apply_config_params(config, &params);
+
+        emit!(ConfigUpdated {
+            admin: config.admin,
+            params,
+        });
+        Ok(())
+    }
+
+    /// Replace the protocol settings and optionally hand over the admin role.
+    /// Fees, limits and the graduation split apply to curves created
+    /// afterwards; the platform treasury and keeper reward apply to all.
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        params: ConfigParams,
+        new_admin: Option<Pubkey>,
+    ) -> Result<()> {
+        validate_config_params(&params)?;
+
+        let config = &mut ctx.accounts.config;
+        apply_config_params(config, &params);
+        if let Some(new_admin) = new_admin {
+            config.admin = new_admin;
+        }
+
+        emit!(ConfigUpdated {
+            admin: config.admin,
+            params,
+        });
+        Ok(())
+    }
+
+    /// Halt or resume trading, graduation and migration for every curve.
+    /// Refunds and settled auction claims stay open. Admin only.
+    pub fn set_protocol_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        ctx.accounts.config.paused = paused;
+
+        emit!(PauseUpdated { mint: None, paused });
+        Ok(())
+    }
+
+    /// Halt or resume trading, graduation and migration for one curve.
+    /// Refunds and settled auction claims stay open. Admin only.
+    pub fn set_curve_paused(ctx: Context<PauseCurve>, paused: bool) -> Result<()> {
+        ctx.accounts.bonding_curve.paused = paused;
+
+        emit!(PauseUpdated {
+            mint: Some(ctx.accounts.mint.key()),
+            paused,
+        });
+        Ok(())
+    }
+
     /// Initialize a new token with AI-generated metadata
     pub fn create_token(
         ctx: Context<CreateToken>,
@@ -88,9 +172,15 @@ pub mod degenie_token_creator {
             mint_to(mint_ctx, initial_supply)?;
         }
 
-        msg!("Token created successfully: {} ({})", name, symbol);
-        msg!("Mint address: {}", mint.key());
-        msg!("Initial supply: {}", initial_supply);
+        emit!(TokenCreated {
+            mint: mint.key(),
+            authority: mint_authority.key(),
+            name,
+            symbol,
+            uri,
+            decimals,
+            initial_supply,
+        });
 
         Ok(())
     }
@@ -171,7 +261,10 @@ pub mod degenie_token_creator {
         );
         freeze_account(freeze_ctx)?;
 
-        msg!("Account frozen");
+        emit!(TokenAccountFrozen {
+            mint: ctx.accounts.mint.key(),
+            account: ctx.accounts.account.key(),
+        });
         Ok(())
     }
 
@@ -187,11 +280,15 @@ pub mod degenie_token_creator {
         );
         thaw_account(thaw_ctx)?;
 
-        msg!("Account thawed");
+        emit!(TokenAccountThawed {
+            mint: ctx.accounts.mint.key(),
+            account: ctx.accounts.account.key(),
+        });
         Ok(())
     }
 
-    /// Initialize bonding curve for token with enhanced features and anti-bot protection
+    /// Initialize bonding curve for token with enhanced features and anti-bot protection.
+    /// Prices are lamports per curve unit and `max_supply` is in curve units.
     pub fn initialize_bonding_curve(
         ctx: Context<InitializeBondingCurve>,
         initial_price: u64,
@@ -199,146 +296,94 @@ pub mod degenie_token_creator {
         max_supply: u64,
         curve_type: CurveType,
         growth_rate: u64,
-        // graduation_threshold removed - now using fixed 500 SOL
+        // graduation_threshold comes from Config
     ) -> Result<()> {
         require!(initial_price > 0, TokenCreatorError::InvalidAmount);
         require!(max_supply > 0, TokenCreatorError::InvalidAmount);
         require!(price_increment > 0, TokenCreatorError::InvalidAmount);
         
-        // Validate growth_rate based on curve type
-        match curve_type {
-            CurveType::Linear => {
-                require!(growth_rate == 0, TokenCreatorError::InvalidAmount);
-            },
-            CurveType::Exponential => {
-                require!(growth_rate > 0 && growth_rate <= 10000, TokenCreatorError::InvalidAmount);
-            },
-            CurveType::Logarithmic => {
-                // Future implementation - allow any value for now
-            }
-        }
-        
+        validate_curve_params(curve_type, growth_rate)?;
+        setup_bonding_curve(ctx.accounts, &ctx.bumps, initial_price, price_increment, max_supply, curve_type, growth_rate)?;
+
         let bonding_curve = &mut ctx.accounts.bonding_curve;
-        let clock = Clock::get()?;
-        
-        bonding_curve.mint = ctx.accounts.mint.key();
-        bonding_curve.current_price = initial_price;
-        bonding_curve.initial_price = initial_price;
-        bonding_curve.price_increment = price_increment;
-        bonding_curve.total_supply = 0;
-        bonding_curve.max_supply = max_supply;
-        bonding_curve.authority = ctx.accounts.authority.key();
-        bonding_curve.bump = ctx.bumps.bonding_curve;
-        bonding_curve.curve_type = curve_type;
-        bonding_curve.growth_rate = growth_rate;
-        bonding_curve.treasury_balance = 0;
-        bonding_curve.total_volume = 0;
-        bonding_curve.graduation_threshold = GRADUATION_THRESHOLD_SOL * LAMPORTS_PER_SOL; // Fixed 500 SOL
-        bonding_curve.is_graduated = false;
-        bonding_curve.creation_fee = 20_000_000; // 0.02 SOL
-        bonding_curve.transaction_fee_bps = 100; // 1%
-        bonding_curve.creator_fee_bps = 50; // 0.5%
-        bonding_curve.platform_fee_bps = 50; // 0.5%
-        
-        // Anti-bot protection settings
-        bonding_curve.creation_timestamp = clock.unix_timestamp;
-        bonding_curve.launch_protection_period = 3600; // 1 hour protection
-        bonding_curve.max_buy_during_protection = 1_000_000_000; // 1 SOL max during protection
-        bonding_curve.transaction_cooldown = 30; // 30 seconds between transactions
-        bonding_curve.max_price_impact_bps = 500; // 5% max price impact
-
-        // Initialize treasury if needed
-        let treasury = &mut ctx.accounts.treasury;
-        if treasury.authority == Pubkey::default() {
-            treasury.authority = ctx.accounts.authority.key();
-            treasury.bump = ctx.bumps.treasury;
-        } else {
-            // Validate existing treasury owner to prevent hijacking
-            require!(
-                treasury.authority == ctx.accounts.authority.key(),
-                TokenCreatorError::InsufficientAuthority
-            );
+
+        // Virtual reserves start at initial_price so the curve opens where the other types do
+        if curve_type == CurveType::ConstantProduct {
+            let virtual_token_reserves = (max_supply as u128)
+                .checked_mul(growth_rate as u128)
+                .ok_or(TokenCreatorError::InvalidAmount)?
+                .checked_div(10000)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+            let virtual_sol_reserves = virtual_token_reserves
+                .checked_mul(initial_price as u128)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+
+            bonding_curve.virtual_token_reserves = u64::try_from(virtual_token_reserves)
+                .map_err(|_| error!(TokenCreatorError::InvalidAmount))?;
+            bonding_curve.virtual_sol_reserves = u64::try_from(virtual_sol_reserves)
+                .map_err(|_| error!(TokenCreatorError::InvalidAmount))?;
         }
 
-        // Charge creation fee
-        let cpi_context = CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.authority.to_account_info(),
-                to: ctx.accounts.treasury.to_account_info(),
-            },
-        );
-        anchor_lang::system_program::transfer(cpi_context, bonding_curve.creation_fee)?;
+        emit!(curve_initialized_event(bonding_curve));
+        Ok(())
+    }
 
-        // Update the on-chain treasury_balance to include the creation fee
-        bonding_curve.treasury_balance = bonding_curve
-            .treasury_balance
-            .saturating_add(bonding_curve.creation_fee);
-        
-        // Update treasury total collected
-        treasury.total_collected = treasury
-            .total_collected
-            .saturating_add(bonding_curve.creation_fee);
-
-        msg!("Enhanced bonding curve initialized with anti-bot protection");
-        msg!("Type: {:?}, Growth: {}%, Initial Price: {}", 
-             curve_type, growth_rate as f64 / 100.0, initial_price);
-        msg!("Protection: {}h, Max buy: {} SOL, Cooldown: {}s", 
-             bonding_curve.launch_protection_period / 3600,
-             bonding_curve.max_buy_during_protection as f64 / 1_000_000_000.0,
-             bonding_curve.transaction_cooldown);
+    /// Initialize a multi-stage bonding curve (e.g. stealth -> growth -> maturity).
+    /// Each stage opens at the closing price of the previous one and the last
+    /// breakpoint becomes the max supply.
+    pub fn initialize_multi_stage_bonding_curve(
+        ctx: Context<InitializeBondingCurve>,
+        initial_price: u64,
+        stages: Vec<CurveStageParams>,
+    ) -> Result<()> {
+        require!(initial_price > 0, TokenCreatorError::InvalidAmount);
+
+        let stages = build_curve_stages(initial_price, &stages)?;
+        let max_supply = stages
+            .last()
+            .map(|stage| stage.end_supply)
+            .ok_or(TokenCreatorError::InvalidCurveStages)?;
+
+        setup_bonding_curve(ctx.accounts, &ctx.bumps, initial_price, 0, max_supply, CurveType::Piecewise, 0)?;
+        ctx.accounts.bonding_curve.stages = stages;
+
+        emit!(curve_initialized_event(&ctx.accounts.bonding_curve));
         Ok(())
     }
 
-    /// Buy tokens through enhanced bonding curve with anti-bot protection
+    /// Buy tokens through enhanced bonding curve with anti-bot protection.
+    /// Fails if fewer than `min_tokens_out` tokens would be minted or the
+    /// transaction lands after `deadline` (unix timestamp). During a presale
+    /// the buyer must pass `presale_proof` for their allowlist entry.
     pub fn buy_tokens(
         ctx: Context<BuyTokens>,
         sol_amount: u64,
+        min_tokens_out: u64,
+        deadline: Option<i64>,
+        presale_proof: Option<PresaleProof>,
     ) -> Result<()> {
         require!(sol_amount > 0, TokenCreatorError::InvalidAmount);
         require!(!ctx.accounts.bonding_curve.is_graduated, TokenCreatorError::AlreadyGraduated);
-        
-        let bonding_curve = &mut ctx.accounts.bonding_curve;
-        let user_tracker = &mut ctx.accounts.user_tracker;
-        let clock = Clock::get()?;
-        
-        // Anti-bot protections
-        let token_age = clock.unix_timestamp - bonding_curve.creation_timestamp;
-        let is_protection_period = token_age < bonding_curve.launch_protection_period;
-        
-        // 1. Rate limiting: Check cooldown between transactions
-        if user_tracker.last_transaction_time > 0 {
-            let time_since_last = clock.unix_timestamp - user_tracker.last_transaction_time;
-            require!(
-                time_since_last >= bonding_curve.transaction_cooldown as i64,
-                TokenCreatorError::TransactionCooldown
-            );
-        }
-        
-        // 2. Protection period limits: Max buy amount during first hour
-        if is_protection_period {
-            require!(
-                sol_amount <= bonding_curve.max_buy_during_protection,
-                TokenCreatorError::ExceedsProtectionLimit
-            );
-        }
-        
-        // 3. Price impact protection: Calculate and validate price impact
-        let price_impact = calculate_price_impact(sol_amount, bonding_curve)?;
         require!(
-            price_impact <= bonding_curve.max_price_impact_bps,
-            TokenCreatorError::ExceedsPriceImpactLimit
+            !is_refund_active(&ctx.accounts.bonding_curve, Clock::get()?.unix_timestamp),
+            TokenCreatorError::RefundModeActive
         );
-        
-        // Calculate transaction fee
-        let transaction_fee = sol_amount
-            .checked_mul(bonding_curve.transaction_fee_bps as u64)
-            .ok_or(TokenCreatorError::InvalidAmount)?
-            .checked_div(10000)
-            .ok_or(TokenCreatorError::InvalidAmount)?;
-        
+        require!(
+            !is_opening_auction_pending(&ctx.accounts.bonding_curve),
+            TokenCreatorError::OpeningAuctionPending
+        );
+        check_not_paused(&ctx.accounts.config, &ctx.accounts.bonding_curve)?;
+        check_deadline(deadline, &Clock::get()?)?;
+
+        let bonding_curve = &ctx.accounts.bonding_curve;
+
+        // Calculate transaction fee and any launch-window sniper tax
+        let transaction_fee = calculate_transaction_fee(sol_amount, bonding_curve)?;
+        let now = Clock::get()?.unix_timestamp;
+        let sniper_tax = calculate_sniper_tax(sol_amount, bonding_curve, now)?;
         let sol_after_fee = sol_amount
             .checked_sub(transaction_fee)
+            .and_then(|amount| amount.checked_sub(sniper_tax))
             .ok_or(TokenCreatorError::InvalidAmount)?;
         
         // Calculate tokens to mint based on curve type
@@ -347,341 +392,215 @@ pub mod degenie_token_creator {
             bonding_curve,
         )?;
 
+        // Only whole base units of the mint can be delivered
+        let raw_tokens = to_raw_units(tokens_to_mint, bonding_curve.decimals)?;
+
         // Reject zero-token purchases to prevent silent SOL burns
-        require!(tokens_to_mint > 0, TokenCreatorError::InvalidAmount);
+        require!(raw_tokens > 0, TokenCreatorError::InvalidAmount);
 
-        // Check max supply
+        // Slippage protection: the fill must meet the user's expectation
+        require!(raw_tokens >= min_tokens_out, TokenCreatorError::SlippageExceeded);
+
+        let tokens_to_mint = to_curve_units(raw_tokens, bonding_curve.decimals)?;
+        execute_buy(
+            ctx.accounts,
+            &ctx.bumps,
+            sol_amount,
+            transaction_fee,
+            sniper_tax,
+            tokens_to_mint,
+            presale_proof,
+        )
+    }
+
+    /// Buy exactly `token_amount` tokens, paying the curve cost plus the
+    /// transaction fee. Fails if that exceeds `max_sol_in` lamports.
+    pub fn buy_exact_tokens(
+        ctx: Context<BuyTokens>,
+        token_amount: u64,
+        max_sol_in: u64,
+        deadline: Option<i64>,
+        presale_proof: Option<PresaleProof>,
+    ) -> Result<()> {
+        require!(token_amount > 0, TokenCreatorError::InvalidAmount);
+        require!(!ctx.accounts.bonding_curve.is_graduated, TokenCreatorError::AlreadyGraduated);
         require!(
-            bonding_curve.total_supply + tokens_to_mint <= bonding_curve.max_supply,
-            TokenCreatorError::ExceedsMaxSupply
+            !is_refund_active(&ctx.accounts.bonding_curve, Clock::get()?.unix_timestamp),
+            TokenCreatorError::RefundModeActive
         );
-
-        // Transfer SOL to treasury
-        let cpi_context = CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.buyer.to_account_info(),
-                to: ctx.accounts.treasury.to_account_info(),
-            },
+        require!(
+            !is_opening_auction_pending(&ctx.accounts.bonding_curve),
+            TokenCreatorError::OpeningAuctionPending
         );
-        anchor_lang::system_program::transfer(cpi_context, sol_amount)?;
-        
-        // Update treasury balance immediately after receiving funds
-        bonding_curve.treasury_balance = bonding_curve
-            .treasury_balance
-            .saturating_add(sol_amount);
-
-        // Split fees between creator and platform
-        if transaction_fee > 0 {
-            let creator_fee = transaction_fee
-                .checked_mul(bonding_curve.creator_fee_bps as u64)
-                .ok_or(TokenCreatorError::InvalidAmount)?
-                .checked_div(bonding_curve.transaction_fee_bps as u64)
-                .ok_or(TokenCreatorError::InvalidAmount)?;
-            
-            // Calculate platform fee as remainder to ensure exact sum
-            let platform_fee = transaction_fee
-                .checked_sub(creator_fee)
-                .ok_or(TokenCreatorError::InvalidAmount)?;
-            
-            // Transfer creator fee with treasury as signer
-            let treasury_seeds = &[
-                b"treasury",
-                ctx.accounts.mint.key().as_ref(),
-                &[ctx.accounts.treasury.bump],
-            ];
-            let signer_seeds = &[&treasury_seeds[..]];
-            
-            // Validate creator account for defense-in-depth
-            require!(
-                ctx.accounts.creator.key() == bonding_curve.authority,
-                TokenCreatorError::InsufficientAuthority
-            );
-            
-            let creator_cpi = CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.treasury.to_account_info(),
-                    to: ctx.accounts.creator.to_account_info(),
-                },
-                signer_seeds,
-            );
-            anchor_lang::system_program::transfer(creator_cpi, creator_fee)?;
-            
-            // Validate platform treasury address
-            let expected_platform_treasury = DEGENIE_PLATFORM_TREASURY.parse::<Pubkey>()
-                .map_err(|_| TokenCreatorError::InvalidAmount)?;
-            require!(
-                ctx.accounts.platform_treasury.key() == expected_platform_treasury,
-                TokenCreatorError::InvalidAmount
-            );
-            
-            // Transfer platform fee to DeGenie treasury with treasury as signer
-            let platform_cpi = CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.treasury.to_account_info(),
-                    to: ctx.accounts.platform_treasury.to_account_info(),
-                },
-                signer_seeds,
-            );
-            anchor_lang::system_program::transfer(platform_cpi, platform_fee)?;
-            
-            // Update treasury balance correctly (subtract fees paid out)
-            bonding_curve.treasury_balance = bonding_curve.treasury_balance
-                .saturating_sub(creator_fee)
-                .saturating_sub(platform_fee);
-        }
+        check_not_paused(&ctx.accounts.config, &ctx.accounts.bonding_curve)?;
+        check_deadline(deadline, &Clock::get()?)?;
 
-        // Mint tokens to buyer
-        let seeds = &[
-            b"bonding_curve",
-            bonding_curve.mint.as_ref(),
-            &[bonding_curve.bump],
-        ];
-        let signer = &[&seeds[..]];
+        let bonding_curve = &ctx.accounts.bonding_curve;
 
-        let mint_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            MintTo {
-                mint: ctx.accounts.mint.to_account_info(),
-                to: ctx.accounts.buyer_token_account.to_account_info(),
-                authority: bonding_curve.to_account_info(),
-            },
-            signer,
+        // The amount must map onto whole curve units to be bought exactly
+        let curve_tokens = to_curve_units(token_amount, bonding_curve.decimals)?;
+        require!(
+            curve_tokens > 0 && to_raw_units(curve_tokens, bonding_curve.decimals)? == token_amount,
+            TokenCreatorError::InvalidAmount
         );
-        mint_to(mint_ctx, tokens_to_mint)?;
 
-        // Update bonding curve state
-        bonding_curve.total_supply += tokens_to_mint;
-        bonding_curve.total_volume += sol_amount;
-        
-        // Update price based on curve type
-        match bonding_curve.curve_type {
-            CurveType::Linear => {
-                let price_increase = bonding_curve.price_increment
-                    .checked_mul(tokens_to_mint)
-                    .ok_or(TokenCreatorError::InvalidAmount)?
-                    .checked_div(1000)
-                    .ok_or(TokenCreatorError::InvalidAmount)?;
-                bonding_curve.current_price = bonding_curve.current_price
-                    .checked_add(price_increase)
-                    .ok_or(TokenCreatorError::InvalidAmount)?;
-            },
-            CurveType::Exponential => {
-                bonding_curve.current_price = calculate_price_exponential(
-                    bonding_curve.initial_price,
-                    bonding_curve.total_supply,
-                    bonding_curve.growth_rate,
-                )?;
-            },
-            CurveType::Logarithmic => {
-                // Future implementation - use same safe arithmetic as Linear for now
-                let price_increase = bonding_curve.price_increment
-                    .checked_mul(tokens_to_mint)
-                    .ok_or(TokenCreatorError::InvalidAmount)?
-                    .checked_div(1000)
-                    .ok_or(TokenCreatorError::InvalidAmount)?;
-                bonding_curve.current_price = bonding_curve.current_price
-                    .checked_add(price_increase)
-                    .ok_or(TokenCreatorError::InvalidAmount)?;
-            }
-        }
-        
-        // Check for graduation
-        let market_cap = bonding_curve.total_supply
-            .checked_mul(bonding_curve.current_price)
-            .ok_or(TokenCreatorError::InvalidAmount)?;
-        
-        if market_cap >= bonding_curve.graduation_threshold {
-            bonding_curve.is_graduated = true;
-            msg!("🎓 Token graduated! Market cap: {} lamports", market_cap);
-            // TODO: Trigger DEX migration
-        }
-        
-        // Update user tracker
-        user_tracker.wallet = ctx.accounts.buyer.key();
-        user_tracker.mint = bonding_curve.mint;
-        user_tracker.last_transaction_time = clock.unix_timestamp;
-        user_tracker.total_bought_sol = user_tracker.total_bought_sol.saturating_add(sol_amount);
-        user_tracker.transaction_count = user_tracker.transaction_count.saturating_add(1);
-        user_tracker.bump = ctx.bumps.user_tracker;
+        // Lamports the curve needs for these tokens, grossed up so the fee
+        // and sniper tax taken on top still leave at least that much
+        let now = Clock::get()?.unix_timestamp;
+        let curve_cost = calculate_sol_for_exact_tokens(curve_tokens, bonding_curve)?;
+        let fee_bps =
+            bonding_curve.transaction_fee_bps + calculate_sniper_tax_bps(bonding_curve, now);
+        let sol_amount = gross_amount_for_net(curve_cost, fee_bps)?;
+        let transaction_fee = calculate_transaction_fee(sol_amount, bonding_curve)?;
+        let sniper_tax = calculate_sniper_tax(sol_amount, bonding_curve, now)?;
 
-        msg!("Bought {} tokens for {} SOL (fee: {} SOL) - Anti-bot protections active", 
-             tokens_to_mint, 
-             sol_amount as f64 / 1_000_000_000.0,
-             transaction_fee as f64 / 1_000_000_000.0);
-        
-        if is_protection_period {
-            msg!("🛡️ Protection period active: {} minutes remaining", 
-                 (bonding_curve.launch_protection_period - token_age) / 60);
-        }
-        
-        Ok(())
+        // Slippage protection: never charge more than the user agreed to
+        require!(sol_amount <= max_sol_in, TokenCreatorError::SlippageExceeded);
+
+        execute_buy(
+            ctx.accounts,
+            &ctx.bumps,
+            sol_amount,
+            transaction_fee,
+            sniper_tax,
+            curve_tokens,
+            presale_proof,
+        )
     }
 
-    /// Sell tokens through enhanced bonding curve
+    /// Sell tokens through enhanced bonding curve.
+    /// Fails if the seller would receive less than `min_sol_out` lamports after
+    /// fees or the transaction lands after `deadline` (unix timestamp).
     pub fn sell_tokens(
         ctx: Context<SellTokens>,
         token_amount: u64,
+        min_sol_out: u64,
+        deadline: Option<i64>,
     ) -> Result<()> {
         require!(token_amount > 0, TokenCreatorError::InvalidAmount);
         require!(!ctx.accounts.bonding_curve.is_graduated, TokenCreatorError::AlreadyGraduated);
+        require!(
+            !is_refund_active(&ctx.accounts.bonding_curve, Clock::get()?.unix_timestamp),
+            TokenCreatorError::RefundModeActive
+        );
+        check_not_paused(&ctx.accounts.config, &ctx.accounts.bonding_curve)?;
+        check_deadline(deadline, &Clock::get()?)?;
         
-        let bonding_curve = &mut ctx.accounts.bonding_curve;
+        let bonding_curve = &ctx.accounts.bonding_curve;
+
+        // Dust below one curve unit stays with the seller
+        let token_amount = to_curve_units(token_amount, bonding_curve.decimals)?;
+        require!(token_amount > 0, TokenCreatorError::InvalidAmount);
         
         // Calculate transaction fee
         let sol_to_return_gross = calculate_sol_for_tokens_with_curve(
             token_amount,
             bonding_curve,
         )?;
-        
-        let transaction_fee = sol_to_return_gross
-            .checked_mul(bonding_curve.transaction_fee_bps as u64)
-            .ok_or(TokenCreatorError::InvalidAmount)?
-            .checked_div(10000)
-            .ok_or(TokenCreatorError::InvalidAmount)?;
-        
+        let transaction_fee = calculate_transaction_fee(sol_to_return_gross, bonding_curve)?;
         let sol_to_return_net = sol_to_return_gross
             .checked_sub(transaction_fee)
             .ok_or(TokenCreatorError::InvalidAmount)?;
 
-        // Burn tokens from seller
-        let burn_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Burn {
-                mint: ctx.accounts.mint.to_account_info(),
-                from: ctx.accounts.seller_token_account.to_account_info(),
-                authority: ctx.accounts.seller.to_account_info(),
-            },
-        );
-        burn(burn_ctx, token_amount)?;
+        // Slippage protection: the payout must meet the user's expectation
+        require!(sol_to_return_net >= min_sol_out, TokenCreatorError::SlippageExceeded);
 
-        // Transfer SOL back to seller with treasury as signer
-        let treasury_seeds = &[
-            b"treasury",
-            ctx.accounts.mint.key().as_ref(),
-            &[ctx.accounts.treasury.bump],
-        ];
-        let signer_seeds = &[&treasury_seeds[..]];
+        execute_sell(ctx.accounts, token_amount, sol_to_return_gross, transaction_fee, sol_to_return_net)
+    }
 
-        let transfer_ctx = CpiContext::new_with_signer(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.treasury.to_account_info(),
-                to: ctx.accounts.seller.to_account_info(),
-            },
-            signer_seeds,
+    /// Sell just enough tokens to receive exactly `sol_out` lamports after fees.
+    /// Fails if that takes more than `max_tokens_in` tokens.
+    pub fn sell_for_exact_sol(
+        ctx: Context<SellTokens>,
+        sol_out: u64,
+        max_tokens_in: u64,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        require!(sol_out > 0, TokenCreatorError::InvalidAmount);
+        require!(!ctx.accounts.bonding_curve.is_graduated, TokenCreatorError::AlreadyGraduated);
+        require!(
+            !is_refund_active(&ctx.accounts.bonding_curve, Clock::get()?.unix_timestamp),
+            TokenCreatorError::RefundModeActive
         );
-        anchor_lang::system_program::transfer(transfer_ctx, sol_to_return_net)?;
+        check_not_paused(&ctx.accounts.config, &ctx.accounts.bonding_curve)?;
+        check_deadline(deadline, &Clock::get()?)?;
 
-        // Update fees
-        if transaction_fee > 0 {
-            let creator_fee = transaction_fee
-                .checked_mul(bonding_curve.creator_fee_bps as u64)
-                .ok_or(TokenCreatorError::InvalidAmount)?
-                .checked_div(bonding_curve.transaction_fee_bps as u64)
-                .ok_or(TokenCreatorError::InvalidAmount)?;
-            
-            // Calculate platform fee as remainder to ensure exact sum
-            let platform_fee = transaction_fee
-                .checked_sub(creator_fee)
-                .ok_or(TokenCreatorError::InvalidAmount)?;
-            
-            // Validate creator account for defense-in-depth
-            require!(
-                ctx.accounts.creator.key() == bonding_curve.authority,
-                TokenCreatorError::InsufficientAuthority
-            );
-            
-            // Transfer creator fee with treasury as signer
-            let creator_cpi = CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.treasury.to_account_info(),
-                    to: ctx.accounts.creator.to_account_info(),
-                },
-                signer_seeds,
-            );
-            anchor_lang::system_program::transfer(creator_cpi, creator_fee)?;
-            
-            // Validate and transfer platform fee
-            let expected_platform_treasury = DEGENIE_PLATFORM_TREASURY.parse::<Pubkey>()
-                .map_err(|_| TokenCreatorError::InvalidAmount)?;
-            require!(
-                ctx.accounts.platform_treasury.key() == expected_platform_treasury,
-                TokenCreatorError::InvalidAmount
-            );
-            
-            let platform_cpi = CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                anchor_lang::system_program::Transfer {
-                    from: ctx.accounts.treasury.to_account_info(),
-                    to: ctx.accounts.platform_treasury.to_account_info(),
-                },
-                signer_seeds,
-            );
-            anchor_lang::system_program::transfer(platform_cpi, platform_fee)?;
-            
-            // Treasury balance will be updated below with the net payout amount
-        }
+        let bonding_curve = &ctx.accounts.bonding_curve;
 
-        // Update bonding curve state
-        bonding_curve.total_supply -= token_amount;
-        bonding_curve.treasury_balance = bonding_curve.treasury_balance
-            .saturating_sub(sol_to_return_gross); // Subtract gross amount since all fees are paid from treasury
-        bonding_curve.total_volume += sol_to_return_gross;
-        
-        // Update price based on curve type
-        match bonding_curve.curve_type {
-            CurveType::Linear => {
-                let price_decrease = bonding_curve.price_increment
-                    .checked_mul(token_amount)
-                    .ok_or(TokenCreatorError::InvalidAmount)?
-                    .checked_div(1000)
-                    .ok_or(TokenCreatorError::InvalidAmount)?;
-                bonding_curve.current_price = bonding_curve.current_price
-                    .saturating_sub(price_decrease);
-            },
-            CurveType::Exponential => {
-                bonding_curve.current_price = calculate_price_exponential(
-                    bonding_curve.initial_price,
-                    bonding_curve.total_supply,
-                    bonding_curve.growth_rate,
-                )?;
-            },
-            CurveType::Logarithmic => {
-                // Future implementation - use same safe arithmetic as Linear
-                let price_decrease = bonding_curve.price_increment
-                    .checked_mul(token_amount)
-                    .ok_or(TokenCreatorError::InvalidAmount)?
-                    .checked_div(1000)
-                    .ok_or(TokenCreatorError::InvalidAmount)?;
-                bonding_curve.current_price = bonding_curve.current_price
-                    .saturating_sub(price_decrease);
-            }
+        let (token_amount, _) = calculate_tokens_for_exact_sol(sol_out, bonding_curve)?;
+
+        // Round up to whole base units of the mint so the payout is still covered
+        let mut raw_tokens = to_raw_units(token_amount, bonding_curve.decimals)?;
+        if to_curve_units(raw_tokens, bonding_curve.decimals)? < token_amount {
+            raw_tokens += 1;
         }
+        let token_amount = to_curve_units(raw_tokens, bonding_curve.decimals)?;
 
-        msg!("Sold {} tokens for {} SOL (fee: {} SOL)", 
-             token_amount, 
-             sol_to_return_gross as f64 / 1_000_000_000.0,
-             transaction_fee as f64 / 1_000_000_000.0);
-        Ok(())
+        // The extra unit must still come out of the seller's balance and the sold supply
+        require!(
+            raw_tokens <= ctx.accounts.seller_token_account.amount
+                && token_amount <= bonding_curve.total_supply,
+            TokenCreatorError::InsufficientBalance
+        );
+        let sol_to_return_gross = calculate_sol_for_tokens_with_curve(token_amount, bonding_curve)?;
+        let transaction_fee = calculate_transaction_fee(sol_to_return_gross, bonding_curve)?;
+
+        // Slippage protection: never burn more than the user agreed to
+        require!(raw_tokens <= max_tokens_in, TokenCreatorError::SlippageExceeded);
+
+        // Pay exactly sol_out; the sub-token rounding remainder stays in the curve
+        execute_sell(ctx.accounts, token_amount, sol_to_return_gross, transaction_fee, sol_out)
+    }
+    
+    /// Simulate `buy_tokens` for `sol_amount` lamports without touching any
+    /// state. The quote is returned through Anchor return data; anti-bot
+    /// limits tied to the buyer's wallet are not evaluated.
+    pub fn quote_buy(ctx: Context<QuoteTrade>, sol_amount: u64) -> Result<TradeQuote> {
+        require!(sol_amount > 0, TokenCreatorError::InvalidAmount);
+        require!(!ctx.accounts.bonding_curve.is_graduated, TokenCreatorError::AlreadyGraduated);
+        require!(
+            !is_refund_active(&ctx.accounts.bonding_curve, Clock::get()?.unix_timestamp),
+            TokenCreatorError::RefundModeActive
+        );
+        require!(
+            !is_opening_auction_pending(&ctx.accounts.bonding_curve),
+            TokenCreatorError::OpeningAuctionPending
+        );
+
+        calculate_buy_quote(sol_amount, &ctx.accounts.bonding_curve, Clock::get()?.unix_timestamp)
+    }
+
+    /// Simulate `sell_tokens` for `token_amount` tokens without touching any
+    /// state. The quote is returned through Anchor return data.
+    pub fn quote_sell(ctx: Context<QuoteTrade>, token_amount: u64) -> Result<TradeQuote> {
+        require!(token_amount > 0, TokenCreatorError::InvalidAmount);
+        require!(!ctx.accounts.bonding_curve.is_graduated, TokenCreatorError::AlreadyGraduated);
+        require!(
+            !is_refund_active(&ctx.accounts.bonding_curve, Clock::get()?.unix_timestamp),
+            TokenCreatorError::RefundModeActive
+        );
+
+        calculate_sell_quote(token_amount, &ctx.accounts.bonding_curve)
     }
     
-    /// Graduate token to Raydium when market cap threshold is reached
+    /// Graduate token to Raydium when market cap threshold is reached.
+    /// Permissionless: any keeper can crank it and earns Config::keeper_reward.
+    /// Trading on the curve stops; liquidity moves in create_raydium_pool.
     pub fn graduate_to_raydium(
         ctx: Context<GraduateToRaydium>,
     ) -> Result<()> {
+        check_not_paused(&ctx.accounts.config, &ctx.accounts.bonding_curve)?;
         let bonding_curve = &mut ctx.accounts.bonding_curve;
         
         // Check if already graduated
         require!(!bonding_curve.is_graduated, TokenCreatorError::AlreadyGraduated);
+        require!(
+            !is_refund_active(bonding_curve, Clock::get()?.unix_timestamp),
+            TokenCreatorError::RefundModeActive
+        );
         
         // Calculate current market cap
-        let market_cap = bonding_curve.total_supply
-            .checked_mul(bonding_curve.current_price)
-            .ok_or(TokenCreatorError::InvalidAmount)?;
+        let market_cap = calculate_market_cap(bonding_curve)?;
         
         // Check if graduation threshold reached
         require!(
@@ -689,469 +608,3970 @@ pub mod degenie_token_creator {
             TokenCreatorError::GraduationThresholdNotMet
         );
         
-        // Calculate liquidity to migrate (85% of treasury)
-        let liquidity_amount = bonding_curve.treasury_balance
-            .checked_mul(85)
-            .ok_or(TokenCreatorError::InvalidAmount)?
-            .checked_div(100)
-            .ok_or(TokenCreatorError::InvalidAmount)?;
-        
-        // Mark as graduated
-        bonding_curve.is_graduated = true;
-        
-        // Update treasury balance to reflect liquidity migration
-        bonding_curve.treasury_balance = bonding_curve
-            .treasury_balance
-            .saturating_sub(liquidity_amount);
-        
-        msg!("🎓 TOKEN GRADUATED!");
-        msg!("Market cap: {} SOL", market_cap as f64 / 1_000_000_000.0);
-        msg!("Liquidity for Raydium: {} SOL", liquidity_amount as f64 / 1_000_000_000.0);
-        msg!("Remaining treasury: {} SOL", 
-             bonding_curve.treasury_balance as f64 / 1_000_000_000.0);
-        
-        // TODO: Actual Raydium pool creation will be handled by separate instruction
-        // This requires Raydium SDK integration
-        
-        Ok(())
+        graduate(
+            bonding_curve,
+            &ctx.accounts.config,
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.keeper.to_account_info(),
+            market_cap,
+            Clock::get()?.unix_timestamp,
+        )
     }
     
-    /// Create liquidity pool on Raydium (separate instruction after graduation)
-    pub fn create_raydium_pool(
-        ctx: Context<CreateRaydiumPool>,
-        base_amount: u64,    // Token amount
-        quote_amount: u64,   // SOL amount
-    ) -> Result<()> {
+    /// Create the post-graduation liquidity pool. Moves the liquidity share of
+    /// the treasury SOL (85%) and a price-continuous amount of the unminted
+    /// supply into program-owned vaults of an in-program constant-product AMM,
+    /// mints the LP tokens to the treasury and burns them so the liquidity is
+    /// permanent. The rest of the treasury is paid out to the platform (10%)
+    /// and the creator (5%). Curves set to migrate to a DEX land here when
+    /// their liquidity share is too small for migrate_to_dex.
+    /// Permissionless so a curve is never stranded after graduating; the
+    /// keeper is paid Config::keeper_reward out of the treasury.
+    pub fn create_raydium_pool(ctx: Context<CreateRaydiumPool>) -> Result<()> {
         let bonding_curve = &ctx.accounts.bonding_curve;
+        check_not_paused(&ctx.accounts.config, bonding_curve)?;
         
         // Ensure token is graduated
         require!(bonding_curve.is_graduated, TokenCreatorError::NotGraduated);
-        
-        // Ensure pool hasn't been created yet
-        require!(!ctx.accounts.pool_state.is_initialized, TokenCreatorError::PoolAlreadyCreated);
-        
-        msg!("Creating Raydium pool...");
-        msg!("Base (token) amount: {}", base_amount);
-        msg!("Quote (SOL) amount: {} SOL", quote_amount as f64 / 1_000_000_000.0);
-        
-        // TODO: Implement actual Raydium pool creation
-        // This will involve:
-        // 1. Creating pool account
-        // 2. Transferring tokens and SOL
-        // 3. Minting LP tokens
-        // 4. Burning LP tokens for permanence
-        
-        Ok(())
-    }
-}
+        let keeper_reward = take_keeper_reward(&mut ctx.accounts.bonding_curve, &ctx.accounts.config);
+        let bonding_curve = &ctx.accounts.bonding_curve;
 
-#[derive(Accounts)]
-#[instruction(name: String, symbol: String)]
-pub struct CreateToken<'info> {
-    #[account(
+        // A DEX target whose liquidity can't cover DEX_MIGRATION_BUDGET falls
+        // back to this pool rather than leaving the curve stranded
+        let (sol_amount, platform_share, creator_payout) = calculate_graduation_split(bonding_curve)?;
+        require!(
+            bonding_curve.migration_target == MigrationTarget::InHousePool
+                || sol_amount <= DEX_MIGRATION_BUDGET,
+            TokenCreatorError::WrongMigrationTarget
+        );
+
+        // Pool opens at the curve's closing price
+        let token_amount = calculate_pool_token_amount(sol_amount, bonding_curve)?;
+        let raw_token_amount = to_raw_units(token_amount, bonding_curve.decimals)?;
+        require!(sol_amount > 0 && raw_token_amount > 0, TokenCreatorError::InvalidAmount);
+        let lp_amount = calculate_initial_lp_amount(sol_amount, raw_token_amount)?;
+
+        // Mint the token side straight into the pool vault
+        let mint_key = ctx.accounts.mint.key();
+        let curve_seeds = &[
+            b"bonding_curve",
+            mint_key.as_ref(),
+            &[bonding_curve.bump],
+        ];
+        let curve_signer = &[&curve_seeds[..]];
+        let mint_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.token_vault.to_account_info(),
+                authority: bonding_curve.to_account_info(),
+            },
+            curve_signer,
+        );
+        mint_to(mint_ctx, raw_token_amount)?;
+
+        // Mint LP tokens to the treasury
+        let pool_seeds = &[
+            b"pool",
+            mint_key.as_ref(),
+            &[ctx.bumps.pool_state],
+        ];
+        let pool_signer = &[&pool_seeds[..]];
+        let lp_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                to: ctx.accounts.treasury_lp_account.to_account_info(),
+                authority: ctx.accounts.pool_state.to_account_info(),
+            },
+            pool_signer,
+        );
+        mint_to(lp_ctx, lp_amount)?;
+
+        // Burn every LP token the treasury received
+        let treasury_seeds = &[
+            b"treasury",
+            mint_key.as_ref(),
+            &[ctx.accounts.treasury.bump],
+        ];
+        let treasury_signer = &[&treasury_seeds[..]];
+        let burn_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                from: ctx.accounts.treasury_lp_account.to_account_info(),
+                authority: ctx.accounts.treasury.to_account_info(),
+            },
+            treasury_signer,
+        );
+        burn(burn_ctx, lp_amount)?;
+
+        // Lamports move after the token CPIs: the runtime only syncs the
+        // accounts a CPI names, so an earlier treasury debit would leave the
+        // instruction unbalanced when the burn reads the treasury
+        move_lamports(
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.sol_vault.to_account_info(),
+            sol_amount,
+        )?;
+
+        pay_graduation_split(
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.platform_treasury.to_account_info(),
+            &ctx.accounts.creator.to_account_info(),
+            &ctx.accounts.keeper.to_account_info(),
+            platform_share,
+            creator_payout,
+            keeper_reward,
+        )?;
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        pool_state.is_initialized = true;
+        pool_state.mint = mint_key;
+        pool_state.lp_mint = ctx.accounts.lp_mint.key();
+        pool_state.token_vault = ctx.accounts.token_vault.key();
+        pool_state.sol_vault = ctx.accounts.sol_vault.key();
+        pool_state.creation_timestamp = Clock::get()?.unix_timestamp;
+        pool_state.bump = ctx.bumps.pool_state;
+        pool_state.sol_vault_bump = ctx.bumps.sol_vault;
+        pool_state.sol_reserves = sol_amount;
+        pool_state.token_reserves = raw_token_amount;
+        pool_state.lp_supply = lp_amount;
+        pool_state.lp_burned = lp_amount;
+
+        ctx.accounts.bonding_curve.migration_target = MigrationTarget::InHousePool;
+        record_migration(
+            &mut ctx.accounts.bonding_curve,
+            (sol_amount, platform_share, creator_payout),
+            token_amount,
+        )?;
+        
+        emit!(PoolCreated {
+            mint: mint_key,
+            migration_target: MigrationTarget::InHousePool,
+            lp_mint: ctx.accounts.lp_mint.key(),
+            sol_amount,
+            token_amount: raw_token_amount,
+            lp_burned: lp_amount,
+            platform_payout: platform_share,
+            creator_payout,
+            keeper: ctx.accounts.keeper.key(),
+            keeper_reward,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        Ok(())
+    }
+
+    /// Choose where the curve's liquidity goes at graduation. Only the
+    /// creator may set it, and only at launch before the first trade.
+    pub fn set_migration_target(
+        ctx: Context<ConfigureLaunch>,
+        migration_target: MigrationTarget,
+    ) -> Result<()> {
+        let bonding_curve = &mut ctx.accounts.bonding_curve;
+        check_launch_unlocked(bonding_curve)?;
+
+        bonding_curve.migration_target = migration_target;
+
+        emit!(launch_configured_event(bonding_curve));
+        Ok(())
+    }
+
+    /// Give the curve until `graduation_deadline` (unix timestamp) to
+    /// graduate, after which it only allows refunds; None removes the
+    /// deadline. It must fall after any opening auction. Only the creator
+    /// may set it, and only before the first trade.
+    pub fn set_graduation_deadline(
+        ctx: Context<ConfigureLaunch>,
+        graduation_deadline: Option<i64>,
+    ) -> Result<()> {
+        let bonding_curve = &mut ctx.accounts.bonding_curve;
+        check_launch_unlocked(bonding_curve)?;
+        if let Some(deadline) = graduation_deadline {
+            require!(deadline > Clock::get()?.unix_timestamp, TokenCreatorError::TransactionExpired);
+            require!(deadline > bonding_curve.opening_auction_end, TokenCreatorError::InvalidConfig);
+        }
+
+        bonding_curve.graduation_deadline = graduation_deadline;
+
+        emit!(launch_configured_event(bonding_curve));
+        Ok(())
+    }
+
+    /// Open the curve with a presale: until `presale_end` (unix timestamp)
+    /// only wallets in `merkle_root` may buy, each up to its allocation in
+    /// place of the protection period's buy and wallet caps. None removes
+    /// the presale. Only the creator may set it, and only
+    /// before the first trade.
+    pub fn set_presale(
+        ctx: Context<ConfigureLaunch>,
+        merkle_root: Option<[u8; 32]>,
+        presale_end: i64,
+    ) -> Result<()> {
+        let bonding_curve = &mut ctx.accounts.bonding_curve;
+        check_launch_unlocked(bonding_curve)?;
+        if merkle_root.is_some() {
+            require!(presale_end > Clock::get()?.unix_timestamp, TokenCreatorError::TransactionExpired);
+            require!(
+                bonding_curve.opening_auction_end == 0,
+                TokenCreatorError::ConflictingLaunchModes
+            );
+        }
+
+        bonding_curve.presale_merkle_root = merkle_root;
+        bonding_curve.presale_end = presale_end;
+
+        emit!(launch_configured_event(bonding_curve));
+        Ok(())
+    }
+
+    /// Open the curve with a batch auction over its first `duration`
+    /// seconds: buyers commit SOL with commit_buy and settle_opening_auction
+    /// then fills them all at one price, so landing first in the opening
+    /// slot gains nothing. 0 removes the auction. It must end before any
+    /// graduation deadline. Only the creator may set it, and only before
+    /// the first trade; set it in the same transaction as the curve
+    /// initializer so nobody can buy ahead of it.
+    pub fn set_opening_auction(ctx: Context<ConfigureLaunch>, duration: i64) -> Result<()> {
+        let bonding_curve = &mut ctx.accounts.bonding_curve;
+        check_launch_unlocked(bonding_curve)?;
+        require!(
+            (0..=MAX_OPENING_AUCTION_DURATION).contains(&duration),
+            TokenCreatorError::InvalidConfig
+        );
+
+        bonding_curve.opening_auction_end = if duration > 0 {
+            let opening_auction_end = bonding_curve.creation_timestamp + duration;
+            require!(
+                opening_auction_end > Clock::get()?.unix_timestamp,
+                TokenCreatorError::TransactionExpired
+            );
+            require!(
+                bonding_curve.presale_merkle_root.is_none(),
+                TokenCreatorError::ConflictingLaunchModes
+            );
+            require!(
+                bonding_curve.graduation_deadline.unwrap_or(i64::MAX) > opening_auction_end,
+                TokenCreatorError::InvalidConfig
+            );
+            opening_auction_end
+        } else {
+            0
+        };
+
+        emit!(launch_configured_event(bonding_curve));
+        Ok(())
+    }
+
+    /// Commit `sol_amount` lamports to the curve's opening auction. The SOL
+    /// waits in the auction escrow until settle_opening_auction fills every
+    /// commit at the same price; the first committer pays the escrow's rent.
+    pub fn commit_buy(ctx: Context<CommitBuy>, sol_amount: u64) -> Result<()> {
+        require!(sol_amount > 0, TokenCreatorError::InvalidAmount);
+        check_not_paused(&ctx.accounts.config, &ctx.accounts.bonding_curve)?;
+        let clock = Clock::get()?;
+        let bonding_curve = &ctx.accounts.bonding_curve;
+        require!(!bonding_curve.is_graduated, TokenCreatorError::AlreadyGraduated);
+        require!(
+            !is_refund_active(bonding_curve, clock.unix_timestamp),
+            TokenCreatorError::RefundModeActive
+        );
+        require!(
+            is_opening_auction_pending(bonding_curve)
+                && clock.unix_timestamp < bonding_curve.opening_auction_end,
+            TokenCreatorError::OpeningAuctionClosed
+        );
+
+        // Commits count toward the protection period's per-wallet total, which
+        // the wallet's buys after the auction share
+        let total_bought_sol = ctx.accounts.user_tracker.total_bought_sol
+            .checked_add(sol_amount)
+            .ok_or(TokenCreatorError::InvalidAmount)?;
+        require!(
+            total_bought_sol <= bonding_curve.max_wallet_buy_during_protection,
+            TokenCreatorError::ExceedsWalletBuyLimit
+        );
+        let buyer_committed_sol = ctx.accounts.auction_commit.sol_amount
+            .checked_add(sol_amount)
+            .ok_or(TokenCreatorError::InvalidAmount)?;
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.buyer.to_account_info(),
+                to: ctx.accounts.auction_escrow.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_context, sol_amount)?;
+
+        let auction_escrow = &mut ctx.accounts.auction_escrow;
+        auction_escrow.mint = ctx.accounts.mint.key();
+        auction_escrow.bump = ctx.bumps.auction_escrow;
+
+        let auction_commit = &mut ctx.accounts.auction_commit;
+        auction_commit.mint = ctx.accounts.mint.key();
+        auction_commit.buyer = ctx.accounts.buyer.key();
+        auction_commit.sol_amount = buyer_committed_sol;
+        auction_commit.bump = ctx.bumps.auction_commit;
+
+        let user_tracker = &mut ctx.accounts.user_tracker;
+        user_tracker.wallet = ctx.accounts.buyer.key();
+        user_tracker.mint = ctx.accounts.mint.key();
+        user_tracker.total_bought_sol = total_bought_sol;
+        user_tracker.bump = ctx.bumps.user_tracker;
+
+        let bonding_curve = &mut ctx.accounts.bonding_curve;
+        bonding_curve.auction_committed_sol = bonding_curve.auction_committed_sol
+            .checked_add(sol_amount)
+            .ok_or(TokenCreatorError::InvalidAmount)?;
+
+        emit!(AuctionCommitted {
+            mint: bonding_curve.mint,
+            buyer: ctx.accounts.buyer.key(),
+            sol_amount,
+            buyer_committed_sol,
+            auction_committed_sol: bonding_curve.auction_committed_sol,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Fill the opening auction once it has ended: all committed SOL buys on
+    /// the curve as a single trade, so every buyer pays the same price.
+    /// Buyers then collect their pro-rata tokens and any unfilled SOL with
+    /// claim_auction_fill. Anyone can crank it. The fill is exempt from
+    /// max_price_impact_bps: it is the whole opening demand at one price,
+    /// not a single wallet moving the curve against the others.
+    pub fn settle_opening_auction(ctx: Context<SettleOpeningAuction>) -> Result<()> {
+        check_not_paused(&ctx.accounts.config, &ctx.accounts.bonding_curve)?;
+        let clock = Clock::get()?;
+        let bonding_curve = &ctx.accounts.bonding_curve;
+        require!(!bonding_curve.is_graduated, TokenCreatorError::AlreadyGraduated);
+        require!(
+            !is_refund_active(bonding_curve, clock.unix_timestamp),
+            TokenCreatorError::RefundModeActive
+        );
+        require!(is_opening_auction_pending(bonding_curve), TokenCreatorError::NoOpeningAuction);
+        require!(
+            clock.unix_timestamp >= bonding_curve.opening_auction_end,
+            TokenCreatorError::OpeningAuctionNotEnded
+        );
+
+        let committed_sol = bonding_curve.auction_committed_sol;
+        let (filled_sol, transaction_fee, tokens) =
+            calculate_auction_fill(committed_sol, bonding_curve)?;
+        if filled_sol > 0 {
+            let auction_escrow = ctx.accounts.auction_escrow
+                .as_ref()
+                .ok_or(TokenCreatorError::InsufficientBalance)?;
+            move_lamports(
+                &auction_escrow.to_account_info(),
+                &ctx.accounts.treasury.to_account_info(),
+                filled_sol,
+            )?;
+        }
+
+        // Booked like one buy; tokens are minted as buyers claim them
+        let bonding_curve = &mut ctx.accounts.bonding_curve;
+        let (creator_fee, platform_fee) = split_transaction_fee(transaction_fee, bonding_curve)?;
+        accrue_fees(bonding_curve, creator_fee, platform_fee)?;
+        let sol_after_fee = filled_sol
+            .checked_sub(transaction_fee)
+            .ok_or(TokenCreatorError::InvalidAmount)?;
+        bonding_curve.treasury_balance = bonding_curve
+            .treasury_balance
+            .saturating_add(sol_after_fee);
+        bonding_curve.curve_sol_balance = bonding_curve
+            .curve_sol_balance
+            .checked_add(sol_after_fee)
+            .ok_or(TokenCreatorError::InvalidAmount)?;
+        apply_buy_to_curve(bonding_curve, sol_after_fee, tokens)?;
+        bonding_curve.total_volume = bonding_curve.total_volume
+            .checked_add(filled_sol)
+            .ok_or(TokenCreatorError::InvalidAmount)?;
+        bonding_curve.auction_filled_sol = filled_sol;
+        bonding_curve.auction_token_amount = tokens;
+        bonding_curve.auction_settled = true;
+
+        emit!(OpeningAuctionSettled {
+            mint: bonding_curve.mint,
+            committed_sol,
+            filled_sol,
+            transaction_fee,
+            token_amount: to_raw_units(tokens, bonding_curve.decimals)?,
+            new_price: bonding_curve.current_price,
+            new_supply: bonding_curve.total_supply,
+            treasury_balance: bonding_curve.treasury_balance,
+            timestamp: clock.unix_timestamp,
+        });
+
+        graduate_if_threshold_met(
+            bonding_curve,
+            &ctx.accounts.config,
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.keeper.to_account_info(),
+            clock.unix_timestamp,
+        )
+    }
+
+    /// Deliver a buyer's share of a settled opening auction: tokens pro rata
+    /// to their commit, plus the unfilled part of it back in SOL. An auction
+    /// left unsettled past the graduation deadline fills nothing, so every
+    /// commit comes back in full. Anyone can crank it for any buyer, even
+    /// while trading is paused; the commit's rent goes back to the buyer.
+    pub fn claim_auction_fill(ctx: Context<ClaimAuctionFill>) -> Result<()> {
+        let bonding_curve = &ctx.accounts.bonding_curve;
+        require!(
+            bonding_curve.auction_settled
+                || is_refund_active(bonding_curve, Clock::get()?.unix_timestamp),
+            TokenCreatorError::OpeningAuctionPending
+        );
+
+        let (tokens, refund_amount) =
+            calculate_auction_share(ctx.accounts.auction_commit.sol_amount, bonding_curve)?;
+        let raw_tokens = to_raw_units(tokens, bonding_curve.decimals)?;
+        if raw_tokens > 0 {
+            let seeds = &[
+                b"bonding_curve",
+                bonding_curve.mint.as_ref(),
+                &[bonding_curve.bump],
+            ];
+            let signer = &[&seeds[..]];
+            let mint_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.buyer_token_account.to_account_info(),
+                    authority: bonding_curve.to_account_info(),
+                },
+                signer,
+            );
+            mint_to(mint_ctx, raw_tokens)?;
+
+            let user_tracker = &mut ctx.accounts.user_tracker;
+            user_tracker.tokens_bought = user_tracker.tokens_bought.saturating_add(raw_tokens);
+        }
+        if refund_amount > 0 {
+            move_lamports(
+                &ctx.accounts.auction_escrow.to_account_info(),
+                &ctx.accounts.buyer.to_account_info(),
+                refund_amount,
+            )?;
+        }
+
+        emit!(AuctionFillClaimed {
+            mint: bonding_curve.mint,
+            buyer: ctx.accounts.buyer.key(),
+            token_amount: raw_tokens,
+            refund_amount,
+        });
+        Ok(())
+    }
+
+    /// Burn `token_amount` tokens of a curve that missed its graduation
+    /// deadline for a pro-rata share of the SOL backing the curve, including
+    /// any sniper tax kept in the treasury. Trading fees, the creation fee and
+    /// sniper tax paid to the creator are not refunded.
+    pub fn claim_refund(ctx: Context<ClaimRefund>, token_amount: u64) -> Result<()> {
+        let bonding_curve = &ctx.accounts.bonding_curve;
+        require!(
+            is_refund_active(bonding_curve, Clock::get()?.unix_timestamp),
+            TokenCreatorError::RefundModeInactive
+        );
+
+        // Dust below one curve unit stays with the holder
+        let curve_tokens = to_curve_units(token_amount, bonding_curve.decimals)?;
+        require!(curve_tokens > 0, TokenCreatorError::InvalidAmount);
+        let refund_amount = calculate_refund_amount(curve_tokens, bonding_curve)?;
+        require!(refund_amount > 0, TokenCreatorError::InvalidAmount);
+
+        let burn_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.mint.to_account_info(),
+                from: ctx.accounts.holder_token_account.to_account_info(),
+                authority: ctx.accounts.holder.to_account_info(),
+            },
+        );
+        burn(burn_ctx, to_raw_units(curve_tokens, bonding_curve.decimals)?)?;
+
+        move_lamports(
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.holder.to_account_info(),
+            refund_amount,
+        )?;
+
+        let bonding_curve = &mut ctx.accounts.bonding_curve;
+        bonding_curve.curve_sol_balance = bonding_curve.curve_sol_balance
+            .checked_sub(refund_amount)
+            .ok_or(TokenCreatorError::InsufficientBalance)?;
+        bonding_curve.treasury_balance = bonding_curve.treasury_balance
+            .checked_sub(refund_amount)
+            .ok_or(TokenCreatorError::InsufficientBalance)?;
+        bonding_curve.total_supply = bonding_curve.total_supply
+            .checked_sub(curve_tokens)
+            .ok_or(TokenCreatorError::InsufficientBalance)?;
+
+        emit!(Refunded {
+            mint: bonding_curve.mint,
+            holder: ctx.accounts.holder.key(),
+            token_amount: to_raw_units(curve_tokens, bonding_curve.decimals)?,
+            refund_amount,
+            remaining_supply: bonding_curve.total_supply,
+            treasury_balance: bonding_curve.treasury_balance,
+        });
+        Ok(())
+    }
+
+    /// Pay the creator the trading fees the curve has accrued for them
+    pub fn claim_creator_fees(ctx: Context<ClaimCreatorFees>) -> Result<()> {
+        check_not_paused(&ctx.accounts.config, &ctx.accounts.bonding_curve)?;
+        let amount = ctx.accounts.bonding_curve.creator_fees_owed;
+        require!(amount > 0, TokenCreatorError::NoFeesToClaim);
+
+        move_lamports(
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.authority.to_account_info(),
+            amount,
+        )?;
+        ctx.accounts.bonding_curve.creator_fees_owed = 0;
+
+        emit!(FeesClaimed {
+            mint: ctx.accounts.mint.key(),
+            recipient: ctx.accounts.authority.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Sweep the platform's accrued trading fees to Config::platform_treasury.
+    /// In refund mode this includes the treasury SOL refunds don't pay out,
+    /// the creation fee. Permissionless since the destination is fixed.
+    pub fn claim_platform_fees(ctx: Context<ClaimPlatformFees>) -> Result<()> {
+        check_not_paused(&ctx.accounts.config, &ctx.accounts.bonding_curve)?;
+        let bonding_curve = &ctx.accounts.bonding_curve;
+        let unrefunded = if is_refund_active(bonding_curve, Clock::get()?.unix_timestamp) {
+            bonding_curve.treasury_balance.saturating_sub(bonding_curve.curve_sol_balance)
+        } else {
+            0
+        };
+        let amount = bonding_curve.platform_fees_owed
+            .checked_add(unrefunded)
+            .ok_or(TokenCreatorError::InvalidAmount)?;
+        require!(amount > 0, TokenCreatorError::NoFeesToClaim);
+
+        move_lamports(
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.platform_treasury.to_account_info(),
+            amount,
+        )?;
+        let bonding_curve = &mut ctx.accounts.bonding_curve;
+        bonding_curve.platform_fees_owed = 0;
+        bonding_curve.treasury_balance -= unrefunded;
+
+        emit!(FeesClaimed {
+            mint: ctx.accounts.mint.key(),
+            recipient: ctx.accounts.platform_treasury.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Create the caller's Referrer account so trades can name them as referrer
+    pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+        let referrer = &mut ctx.accounts.referrer;
+        referrer.wallet = ctx.accounts.wallet.key();
+        referrer.fees_owed = 0;
+        referrer.total_earned = 0;
+        referrer.bump = ctx.bumps.referrer;
+        Ok(())
+    }
+
+    /// Pay a referrer everything they have earned across all curves
+    pub fn claim_referral_fees(ctx: Context<ClaimReferralFees>) -> Result<()> {
+        require!(!ctx.accounts.config.paused, TokenCreatorError::TradingPaused);
+        let amount = ctx.accounts.referrer.fees_owed;
+        require!(amount > 0, TokenCreatorError::NoFeesToClaim);
+
+        move_lamports(
+            &ctx.accounts.referrer.to_account_info(),
+            &ctx.accounts.wallet.to_account_info(),
+            amount,
+        )?;
+        ctx.accounts.referrer.fees_owed = 0;
+
+        emit!(ReferralFeesClaimed {
+            wallet: ctx.accounts.wallet.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Migrate a graduated curve into the external DEX chosen with
+    /// set_migration_target. The liquidity share of the treasury (less
+    /// DEX_MIGRATION_BUDGET) is wrapped and paired with a price-continuous
+    /// amount of the unminted supply, both held by the migration authority
+    /// PDA, which then creates the pool through the DEX's own instruction and
+    /// burns the LP tokens it receives. The platform and creator shares are
+    /// paid out as in create_raydium_pool.
+    /// The keeper fronts the pool's SOL and the budget and is repaid from
+    /// the treasury once every CPI is done: the runtime only syncs the
+    /// accounts a CPI names, so debiting the treasury first would leave the
+    /// instruction unbalanced.
+    /// The DEX's pool-creation accounts are passed as remaining accounts in
+    /// the order the DEX expects (see MigrationTarget::dex_account_layout);
+    /// anything the DEX needs up front, such as an OpenBook market for
+    /// Raydium AMM v4, must already exist.
+    pub fn migrate_to_dex<'info>(
+        ctx: Context<'_, '_, '_, 'info, MigrateToDex<'info>>,
+    ) -> Result<()> {
+        let bonding_curve = &ctx.accounts.bonding_curve;
+        check_not_paused(&ctx.accounts.config, bonding_curve)?;
+
+        require!(bonding_curve.is_graduated, TokenCreatorError::NotGraduated);
+        require!(!bonding_curve.is_migrated, TokenCreatorError::PoolAlreadyCreated);
+        let keeper_reward = take_keeper_reward(&mut ctx.accounts.bonding_curve, &ctx.accounts.config);
+        let bonding_curve = &ctx.accounts.bonding_curve;
+
+        let target = bonding_curve.migration_target;
+        require!(
+            target.program_id() == Some(ctx.accounts.dex_program.key()),
+            TokenCreatorError::WrongMigrationTarget
+        );
+        let mint_key = ctx.accounts.mint.key();
+        let layout = target
+            .dex_account_layout(&mint_key)
+            .ok_or(TokenCreatorError::WrongMigrationTarget)?;
+        let dex_accounts = ctx.remaining_accounts;
+        validate_dex_accounts(&layout, dex_accounts, ctx.accounts)?;
+
+        // Pool opens at the curve's closing price
+        let (sol_amount, platform_share, creator_payout) = calculate_graduation_split(bonding_curve)?;
+        require!(
+            sol_amount > DEX_MIGRATION_BUDGET,
+            TokenCreatorError::LiquidityBelowMigrationBudget
+        );
+        let deposit_amount = sol_amount - DEX_MIGRATION_BUDGET;
+        let token_amount = calculate_pool_token_amount(deposit_amount, bonding_curve)?;
+        let raw_token_amount = to_raw_units(token_amount, bonding_curve.decimals)?;
+        require!(deposit_amount > 0 && raw_token_amount > 0, TokenCreatorError::InvalidAmount);
+
+        // Stage the SOL side as wrapped SOL; the budget pays the DEX's fees and rent
+        for (to, amount) in [
+            (ctx.accounts.migration_wsol_account.to_account_info(), deposit_amount),
+            (ctx.accounts.migration_authority.to_account_info(), DEX_MIGRATION_BUDGET),
+        ] {
+            let fund_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.keeper.to_account_info(),
+                    to,
+                },
+            );
+            anchor_lang::system_program::transfer(fund_ctx, amount)?;
+        }
+        let sync_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SyncNative {
+                account: ctx.accounts.migration_wsol_account.to_account_info(),
+            },
+        );
+        sync_native(sync_ctx)?;
+
+        // Stage the token side
+        let curve_seeds = &[
+            b"bonding_curve",
+            mint_key.as_ref(),
+            &[bonding_curve.bump],
+        ];
+        let curve_signer = &[&curve_seeds[..]];
+        let mint_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.migration_token_account.to_account_info(),
+                authority: bonding_curve.to_account_info(),
+            },
+            curve_signer,
+        );
+        mint_to(mint_ctx, raw_token_amount)?;
+
+        // Create the pool, signing as the migration authority
+        let authority_seeds = &[
+            b"migration_authority",
+            mint_key.as_ref(),
+            &[ctx.bumps.migration_authority],
+        ];
+        let authority_signer = &[&authority_seeds[..]];
+        let account_metas = dex_accounts
+            .iter()
+            .enumerate()
+            .map(|(index, account)| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer || index == layout.payer,
+                is_writable: account.is_writable,
+            })
+            .collect();
+        let open_time = Clock::get()?.unix_timestamp.max(0) as u64;
+        let create_pool_ix = build_migration_instruction(
+            target,
+            &mint_key,
+            account_metas,
+            raw_token_amount,
+            deposit_amount,
+            open_time,
+        )?;
+        let mut account_infos = dex_accounts.to_vec();
+        account_infos.push(ctx.accounts.dex_program.to_account_info());
+        invoke_signed(&create_pool_ix, &account_infos, authority_signer)?;
+
+        // Burn every LP token the DEX minted to the migration authority
+        let lp_mint = &dex_accounts[layout.lp_mint];
+        let lp_account = &dex_accounts[layout.lp_account];
+        let lp_amount = {
+            let lp_data = lp_account.try_borrow_data()?;
+            let lp_token_account = TokenAccount::try_deserialize(&mut &lp_data[..])?;
+            require!(
+                lp_token_account.owner == ctx.accounts.migration_authority.key()
+                    && lp_token_account.mint == lp_mint.key(),
+                TokenCreatorError::InvalidMigrationAccounts
+            );
+            lp_token_account.amount
+        };
+        let burn_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: lp_mint.clone(),
+                from: lp_account.clone(),
+                authority: ctx.accounts.migration_authority.to_account_info(),
+            },
+            authority_signer,
+        );
+        burn(burn_ctx, lp_amount)?;
+
+        // Hand back whatever the DEX left of the budget
+        let budget_left = ctx.accounts.migration_authority.lamports();
+        if budget_left > 0 {
+            let refund_ctx = CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.migration_authority.to_account_info(),
+                    to: ctx.accounts.platform_treasury.to_account_info(),
+                },
+                authority_signer,
+            );
+            anchor_lang::system_program::transfer(refund_ctx, budget_left)?;
+        }
+
+        // Repay the keeper, then pay out the rest of the split
+        move_lamports(
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.keeper.to_account_info(),
+            sol_amount,
+        )?;
+        pay_graduation_split(
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.platform_treasury.to_account_info(),
+            &ctx.accounts.creator.to_account_info(),
+            &ctx.accounts.keeper.to_account_info(),
+            platform_share,
+            creator_payout,
+            keeper_reward,
+        )?;
+
+        record_migration(
+            &mut ctx.accounts.bonding_curve,
+            (sol_amount, platform_share, creator_payout),
+            token_amount,
+        )?;
+
+        emit!(PoolCreated {
+            mint: mint_key,
+            migration_target: target,
+            lp_mint: lp_mint.key(),
+            sol_amount: deposit_amount,
+            token_amount: raw_token_amount,
+            lp_burned: lp_amount,
+            platform_payout: platform_share,
+            creator_payout,
+            keeper: ctx.accounts.keeper.key(),
+            keeper_reward,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Swap against a graduated token's pool. With `sol_to_token` the user
+    /// pays `amount_in` lamports for tokens; otherwise sells `amount_in` raw
+    /// tokens for SOL.
+    pub fn swap_on_pool(
+        ctx: Context<SwapOnPool>,
+        amount_in: u64,
+        min_amount_out: u64,
+        sol_to_token: bool,
+    ) -> Result<()> {
+        require!(amount_in > 0, TokenCreatorError::InvalidAmount);
+        check_not_paused(&ctx.accounts.config, &ctx.accounts.bonding_curve)?;
+
+        let pool_state = &ctx.accounts.pool_state;
+
+        // The vault is a system account, so its rent-exempt minimum is kept
+        // out of the SOL side the pool trades against
+        let sol_vault = ctx.accounts.sol_vault.to_account_info();
+        let sol_reserves = sol_vault.lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(sol_vault.data_len()))
+            .min(pool_state.sol_reserves);
+        let amount_out = if sol_to_token {
+            calculate_pool_swap_output(amount_in, sol_reserves, pool_state.token_reserves)?
+        } else {
+            calculate_pool_swap_output(amount_in, pool_state.token_reserves, sol_reserves)?
+        };
+        require!(amount_out > 0, TokenCreatorError::InvalidAmount);
+        require!(amount_out >= min_amount_out, TokenCreatorError::SlippageExceeded);
+
+        let mint_key = ctx.accounts.mint.key();
+        if sol_to_token {
+            let pay_ctx = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.user.to_account_info(),
+                    to: ctx.accounts.sol_vault.to_account_info(),
+                },
+            );
+            anchor_lang::system_program::transfer(pay_ctx, amount_in)?;
+
+            let pool_seeds = &[
+                b"pool",
+                mint_key.as_ref(),
+                &[pool_state.bump],
+            ];
+
+            let pool_signer = &[&pool_seeds[..]];
+            let payout_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.token_vault.to_account_info(),
+                    to: ctx.accounts.user_token_account.to_account_info(),
+                    authority: pool_state.to_account_info(),
+                },
+                pool_signer,
+            );
+            transfer(payout_ctx, amount_out)?;
+        } else {
+            let pay_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.user_token_account.to_account_info(),
+                    to: ctx.accounts.token_vault.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            );
+            transfer(pay_ctx, amount_in)?;
+
+            let vault_seeds = &[
+                b"pool_sol_vault",
+                mint_key.as_ref(),
+                &[pool_state.sol_vault_bump],
+            ];
+
+            let vault_signer = &[&vault_seeds[..]];
+            let payout_ctx = CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.sol_vault.to_account_info(),
+                    to: ctx.accounts.user.to_account_info(),
+                },
+                vault_signer,
+            );
+            anchor_lang::system_program::transfer(payout_ctx, amount_out)?;
+        }
+
+        let pool_state = &mut ctx.accounts.pool_state;
+        let (sol_reserves, token_reserves) = if sol_to_token {
+            (
+                pool_state.sol_reserves.checked_add(amount_in),
+                pool_state.token_reserves.checked_sub(amount_out),
+            )
+        } else {
+            (
+                pool_state.sol_reserves.checked_sub(amount_out),
+                pool_state.token_reserves.checked_add(amount_in),
+            )
+        };
+        pool_state.sol_reserves = sol_reserves.ok_or(TokenCreatorError::InvalidAmount)?;
+        pool_state.token_reserves = token_reserves.ok_or(TokenCreatorError::InvalidAmount)?;
+
+        emit!(PoolSwap {
+            mint: mint_key,
+            user: ctx.accounts.user.key(),
+            side: if sol_to_token { TradeSide::Buy } else { TradeSide::Sell },
+            amount_in,
+            amount_out,
+            sol_reserves: pool_state.sol_reserves,
+            token_reserves: pool_state.token_reserves,
+        });
+        Ok(())
+    }
+}
+
+/// Shared tail of buy_tokens and buy_exact_tokens: anti-bot checks, payment,
+/// fee accrual, minting and curve/user state updates
+fn execute_buy(
+    accounts: &mut BuyTokens,
+    bumps: &BuyTokensBumps,
+    sol_amount: u64,
+    transaction_fee: u64,
+    sniper_tax: u64,
+    tokens_to_mint: u64,
+    presale_proof: Option<PresaleProof>,
+) -> Result<()> {
+    let bonding_curve = &mut accounts.bonding_curve;
+    let user_tracker = &mut accounts.user_tracker;
+    let clock = Clock::get()?;
+
+    // Presale: only allowlisted wallets may buy, each up to its allocation
+    let is_presale = is_presale_active(bonding_curve, clock.unix_timestamp);
+    if is_presale {
+        let presale_proof = presale_proof.ok_or(TokenCreatorError::NotOnPresaleAllowlist)?;
+        let buyer = accounts.buyer.key();
+        require!(
+            bonding_curve.presale_merkle_root.is_some_and(|merkle_root| verify_presale_proof(
+                &merkle_root,
+                &buyer,
+                presale_proof.allocation,
+                &presale_proof.proof,
+            )),
+            TokenCreatorError::NotOnPresaleAllowlist
+        );
+
+        let presale_bought_sol = user_tracker.presale_bought_sol.saturating_add(sol_amount);
+        require!(
+            presale_bought_sol <= presale_proof.allocation,
+            TokenCreatorError::ExceedsPresaleAllocation
+        );
+        user_tracker.presale_bought_sol = presale_bought_sol;
+    }
+
+    // Anti-bot protections
+    let token_age = clock.unix_timestamp - bonding_curve.creation_timestamp;
+    let is_protection_period = token_age < bonding_curve.launch_protection_period;
+
+    // 1. Rate limiting: Check cooldown between transactions
+    if user_tracker.last_transaction_time > 0 {
+        let time_since_last = clock.unix_timestamp - user_tracker.last_transaction_time;
+        require!(
+            time_since_last >= bonding_curve.transaction_cooldown as i64,
+            TokenCreatorError::TransactionCooldown
+        );
+    }
+
+    // 2. Protection period limits: Max buy amount during first hour. Presale
+    // buys are capped by their allocation instead.
+    if is_protection_period && !is_presale {
+        require!(
+            sol_amount <= bonding_curve.max_buy_during_protection,
+            TokenCreatorError::ExceedsProtectionLimit
+        );
+
+        // ...and per wallet across the whole period, in SOL spent and tokens
+        // bought. Tokens are counted on the tracker rather than the token
+        // account, which the buyer can empty or swap for a fresh one.
+        require!(
+            user_tracker.total_bought_sol.saturating_add(sol_amount)
+                <= bonding_curve.max_wallet_buy_during_protection,
+            TokenCreatorError::ExceedsWalletBuyLimit
+        );
+        if let Some(max_holding) = calculate_max_wallet_holding(bonding_curve)? {
+            let holding = user_tracker
+                .tokens_bought
+                .saturating_add(to_raw_units(tokens_to_mint, bonding_curve.decimals)?);
+            require!(holding <= max_holding, TokenCreatorError::ExceedsWalletHoldingLimit);
+        }
+    }
+
+    let sol_after_fee = sol_amount
+        .checked_sub(transaction_fee)
+        .and_then(|amount| amount.checked_sub(sniper_tax))
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+
+    // 3. Price impact protection: only the SOL that reaches the curve moves
+    // its price, so fees and the sniper tax don't count
+    let price_impact = calculate_price_impact(sol_after_fee, bonding_curve)?;
+    require!(
+        price_impact <= bonding_curve.max_price_impact_bps,
+        TokenCreatorError::ExceedsPriceImpactLimit
+    );
+
+    // Check max supply
+    require!(
+        bonding_curve.total_supply + tokens_to_mint <= bonding_curve.max_supply,
+        TokenCreatorError::ExceedsMaxSupply
+    );
+
+    // Transfer SOL to treasury
+    let cpi_context = CpiContext::new(
+        accounts.system_program.to_account_info(),
+        anchor_lang::system_program::Transfer {
+            from: accounts.buyer.to_account_info(),
+            to: accounts.treasury.to_account_info(),
+        },
+    );
+    anchor_lang::system_program::transfer(cpi_context, sol_amount)?;
+
+    // The referrer is paid right away; the other fees stay in the treasury
+    // until claimed and only the rest backs the curve
+    let (creator_fee, platform_fee) = split_transaction_fee(transaction_fee, bonding_curve)?;
+    let referral_fee = pay_referral_fee(
+        &accounts.treasury.to_account_info(),
+        accounts.referrer.as_mut(),
+        &accounts.buyer.key(),
+        transaction_fee,
+        bonding_curve,
+    )?;
+    let platform_fee = platform_fee
+        .checked_sub(referral_fee)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    accrue_fees(bonding_curve, creator_fee, platform_fee)?;
+    bonding_curve.treasury_balance = bonding_curve
+        .treasury_balance
+        .saturating_add(sol_after_fee);
+    bonding_curve.curve_sol_balance = bonding_curve
+        .curve_sol_balance
+        .checked_add(sol_after_fee)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+
+    // The sniper tax goes wherever the curve was set up to send it. Kept in
+    // the treasury, it backs refunds too if the curve misses its deadline.
+    match bonding_curve.sniper_tax_recipient {
+        SniperTaxRecipient::Treasury => {
+            bonding_curve.treasury_balance = bonding_curve
+                .treasury_balance
+                .saturating_add(sniper_tax);
+            bonding_curve.curve_sol_balance = bonding_curve
+                .curve_sol_balance
+                .checked_add(sniper_tax)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+        },
+        SniperTaxRecipient::Creator => accrue_fees(bonding_curve, sniper_tax, 0)?,
+    }
+
+    // Mint tokens to buyer
+    let seeds = &[
+        b"bonding_curve",
+        bonding_curve.mint.as_ref(),
+        &[bonding_curve.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let mint_ctx = CpiContext::new_with_signer(
+        accounts.token_program.to_account_info(),
+        MintTo {
+            mint: accounts.mint.to_account_info(),
+            to: accounts.buyer_token_account.to_account_info(),
+            authority: bonding_curve.to_account_info(),
+        },
+        signer,
+    );
+    let raw_tokens = to_raw_units(tokens_to_mint, bonding_curve.decimals)?;
+    mint_to(mint_ctx, raw_tokens)?;
+
+    // Update bonding curve state
+    apply_buy_to_curve(bonding_curve, sol_after_fee, tokens_to_mint)?;
+    bonding_curve.total_volume += sol_amount;
+
+    emit!(Trade {
+        mint: bonding_curve.mint,
+        user: accounts.buyer.key(),
+        side: TradeSide::Buy,
+        token_amount: raw_tokens,
+        sol_amount,
+        transaction_fee,
+        creator_fee,
+        platform_fee,
+        referrer: accounts.referrer.as_ref().map(|referrer| referrer.wallet),
+        referral_fee,
+        sniper_tax,
+        new_price: bonding_curve.current_price,
+        new_supply: bonding_curve.total_supply,
+        treasury_balance: bonding_curve.treasury_balance,
+        timestamp: clock.unix_timestamp,
+    });
+
+    graduate_if_threshold_met(
+        bonding_curve,
+        &accounts.config,
+        &accounts.treasury.to_account_info(),
+        &accounts.buyer.to_account_info(),
+        clock.unix_timestamp,
+    )?;
+
+    // Update user tracker
+    user_tracker.wallet = accounts.buyer.key();
+    user_tracker.mint = bonding_curve.mint;
+    user_tracker.last_transaction_time = clock.unix_timestamp;
+    user_tracker.total_bought_sol = user_tracker.total_bought_sol.saturating_add(sol_amount);
+    user_tracker.tokens_bought = user_tracker.tokens_bought.saturating_add(raw_tokens);
+    user_tracker.transaction_count = user_tracker.transaction_count.saturating_add(1);
+    user_tracker.bump = bumps.user_tracker;
+
+    if is_protection_period {
+        msg!("🛡️ Protection period active: {} minutes remaining", 
+             (bonding_curve.launch_protection_period - token_age) / 60);
+    }
+
+    Ok(())
+}
+
+/// Shared tail of sell_tokens and sell_for_exact_sol: burn, payout, fee accrual
+/// and curve state updates. `token_amount` is in curve units and `sol_out` is
+/// what the seller receives.
+fn execute_sell(
+    accounts: &mut SellTokens,
+    token_amount: u64,
+    sol_to_return_gross: u64,
+    transaction_fee: u64,
+    sol_out: u64,
+) -> Result<()> {
+    let bonding_curve = &accounts.bonding_curve;
+
+    // Price impact protection: large dumps must be split like large buys
+    let price_impact = calculate_sell_price_impact(token_amount, bonding_curve)?;
+    require!(
+        price_impact <= bonding_curve.max_sell_price_impact_bps,
+        TokenCreatorError::ExceedsPriceImpactLimit
+    );
+
+    // Burn tokens from seller
+    let burn_ctx = CpiContext::new(
+        accounts.token_program.to_account_info(),
+        Burn {
+            mint: accounts.mint.to_account_info(),
+            from: accounts.seller_token_account.to_account_info(),
+            authority: accounts.seller.to_account_info(),
+        },
+    );
+    burn(burn_ctx, to_raw_units(token_amount, bonding_curve.decimals)?)?;
+
+    // Pay the seller; the fee stays in the treasury until claimed
+    move_lamports(
+        &accounts.treasury.to_account_info(),
+        &accounts.seller.to_account_info(),
+        sol_out,
+    )?;
+    let (creator_fee, platform_fee) = split_transaction_fee(transaction_fee, bonding_curve)?;
+    let referral_fee = pay_referral_fee(
+        &accounts.treasury.to_account_info(),
+        accounts.referrer.as_mut(),
+        &accounts.seller.key(),
+        transaction_fee,
+        bonding_curve,
+    )?;
+    let platform_fee = platform_fee
+        .checked_sub(referral_fee)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+
+    // Update bonding curve state. The payout and the fees both come out of
+    // the curve's SOL.
+    let bonding_curve = &mut accounts.bonding_curve;
+    accrue_fees(bonding_curve, creator_fee, platform_fee)?;
+    let sol_released = sol_out
+        .checked_add(transaction_fee)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    bonding_curve.treasury_balance = bonding_curve.treasury_balance
+        .saturating_sub(sol_released);
+    bonding_curve.curve_sol_balance = bonding_curve.curve_sol_balance
+        .saturating_sub(sol_released);
+    bonding_curve.total_volume += sol_to_return_gross;
+    apply_sell_to_curve(bonding_curve, sol_released, token_amount)?;
+
+    emit!(Trade {
+        mint: bonding_curve.mint,
+        user: accounts.seller.key(),
+        side: TradeSide::Sell,
+        token_amount: to_raw_units(token_amount, bonding_curve.decimals)?,
+        sol_amount: sol_out,
+        transaction_fee,
+        creator_fee,
+        platform_fee,
+        referrer: accounts.referrer.as_ref().map(|referrer| referrer.wallet),
+        referral_fee,
+        sniper_tax: 0,
+        new_price: bonding_curve.current_price,
+        new_supply: bonding_curve.total_supply,
+        treasury_balance: bonding_curve.treasury_balance,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// Graduate the curve once its market cap reaches the threshold, rewarding
+/// `keeper` for the trade or crank that got it there. Any keeper can then
+/// migrate liquidity via create_raydium_pool or migrate_to_dex.
+fn graduate_if_threshold_met<'info>(
+    bonding_curve: &mut BondingCurve,
+    config: &Config,
+    treasury: &AccountInfo<'info>,
+    keeper: &AccountInfo<'info>,
+    timestamp: i64,
+) -> Result<()> {
+    let market_cap = calculate_market_cap(bonding_curve)?;
+    if market_cap >= bonding_curve.graduation_threshold {
+        graduate(bonding_curve, config, treasury, keeper, market_cap, timestamp)?;
+    }
+    Ok(())
+}
+
+/// Mark the curve graduated and pay `keeper` its reward. Lamports move here,
+/// so callers must have made every CPI of the instruction already.
+fn graduate<'info>(
+    bonding_curve: &mut BondingCurve,
+    config: &Config,
+    treasury: &AccountInfo<'info>,
+    keeper: &AccountInfo<'info>,
+    market_cap: u64,
+    timestamp: i64,
+) -> Result<()> {
+    bonding_curve.is_graduated = true;
+    let keeper_reward = take_keeper_reward(bonding_curve, config);
+    move_lamports(treasury, keeper, keeper_reward)?;
+
+    let (liquidity_amount, _, _) = calculate_graduation_split(bonding_curve)?;
+    emit!(Graduated {
+        mint: bonding_curve.mint,
+        market_cap,
+        liquidity_amount,
+        keeper: keeper.key(),
+        keeper_reward,
+        timestamp,
+    });
+    Ok(())
+}
+
+/// Book Config::keeper_reward for a graduation or migration crank. It comes
+/// off the top of treasury_balance, ahead of the graduation split, so it is
+/// paid whatever the shares are; the caller moves the lamports.
+fn take_keeper_reward(bonding_curve: &mut BondingCurve, config: &Config) -> u64 {
+    let keeper_reward = std::cmp::min(config.keeper_reward, bonding_curve.treasury_balance);
+    bonding_curve.treasury_balance -= keeper_reward;
+    bonding_curve.graduation_keeper_reward = bonding_curve
+        .graduation_keeper_reward
+        .saturating_add(keeper_reward);
+    keeper_reward
+}
+
+/// Move lamports out of a program-owned account. The system program can't
+/// debit accounts it doesn't own, so PDAs like the treasury are debited here.
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let remaining = from.lamports()
+        .checked_sub(amount)
+        .ok_or(TokenCreatorError::InsufficientBalance)?;
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(from.data_len());
+    require!(remaining >= rent_exempt_minimum, TokenCreatorError::InsufficientBalance);
+
+    **from.try_borrow_mut_lamports()? = remaining;
+    **to.try_borrow_mut_lamports()? = to.lamports()
+        .checked_add(amount)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    Ok(())
+}
+
+/// Pay the platform and creator graduation shares out of the treasury,
+/// along with the migration keeper's reward
+fn pay_graduation_split<'info>(
+    treasury: &AccountInfo<'info>,
+    platform_treasury: &AccountInfo<'info>,
+    creator: &AccountInfo<'info>,
+    keeper: &AccountInfo<'info>,
+    platform_share: u64,
+    creator_payout: u64,
+    keeper_reward: u64,
+) -> Result<()> {
+    move_lamports(treasury, platform_treasury, platform_share)?;
+    move_lamports(treasury, creator, creator_payout)?;
+    move_lamports(treasury, keeper, keeper_reward)
+}
+
+/// Book a finished migration on the curve: the treasury split
+/// (liquidity, platform, creator) has left the treasury and `token_amount`
+/// curve units were minted for the pool
+fn record_migration(
+    bonding_curve: &mut BondingCurve,
+    graduation_split: (u64, u64, u64),
+    token_amount: u64,
+) -> Result<()> {
+    let (sol_amount, platform_share, creator_payout) = graduation_split;
+    bonding_curve.treasury_balance = bonding_curve.treasury_balance
+        .saturating_sub(sol_amount)
+        .saturating_sub(platform_share)
+        .saturating_sub(creator_payout);
+    bonding_curve.total_supply = bonding_curve.total_supply
+        .checked_add(token_amount)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    bonding_curve.is_migrated = true;
+    bonding_curve.graduation_platform_payout = platform_share;
+    bonding_curve.graduation_creator_payout = creator_payout;
+    Ok(())
+}
+
+/// Check that the DEX accounts forwarded to migrate_to_dex pay and deposit
+/// from the migration authority's staging accounts
+fn validate_dex_accounts(
+    layout: &DexAccountLayout,
+    dex_accounts: &[AccountInfo],
+    accounts: &MigrateToDex,
+) -> Result<()> {
+    require!(
+        dex_accounts.len() == layout.account_count,
+        TokenCreatorError::InvalidMigrationAccounts
+    );
+
+    let expected = [
+        (layout.payer, accounts.migration_authority.key()),
+        (layout.token_mint, accounts.mint.key()),
+        (layout.sol_mint, accounts.native_mint.key()),
+        (layout.token_account, accounts.migration_token_account.key()),
+        (layout.sol_account, accounts.migration_wsol_account.key()),
+    ];
+    for (index, key) in expected {
+        require!(
+            dex_accounts[index].key() == key,
+            TokenCreatorError::InvalidMigrationAccounts
+        );
+    }
+    Ok(())
+}
+
+/// Move the referrer's cut of `transaction_fee` from the treasury into its
+/// Referrer account. Returns the cut, which comes out of the platform fee.
+fn pay_referral_fee<'info>(
+    treasury: &AccountInfo<'info>,
+    referrer: Option<&mut Account<'info, Referrer>>,
+    trader: &Pubkey,
+    transaction_fee: u64,
+    bonding_curve: &BondingCurve,
+) -> Result<u64> {
+    let Some(referrer) = referrer else {
+        return Ok(0);
+    };
+    require!(referrer.wallet != *trader, TokenCreatorError::SelfReferral);
+
+    let referral_fee = calculate_referral_fee(transaction_fee, bonding_curve)?;
+    if referral_fee > 0 {
+        move_lamports(treasury, &referrer.to_account_info(), referral_fee)?;
+        referrer.fees_owed = referrer.fees_owed
+            .checked_add(referral_fee)
+            .ok_or(TokenCreatorError::InvalidAmount)?;
+        referrer.total_earned = referrer.total_earned.saturating_add(referral_fee);
+    }
+    Ok(referral_fee)
+}
+
+/// Book trading fees left in the treasury for the creator and the platform
+fn accrue_fees(bonding_curve: &mut BondingCurve, creator_fee: u64, platform_fee: u64) -> Result<()> {
+    bonding_curve.creator_fees_owed = bonding_curve.creator_fees_owed
+        .checked_add(creator_fee)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    bonding_curve.platform_fees_owed = bonding_curve.platform_fees_owed
+        .checked_add(platform_fee)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    Ok(())
+}
+
+/// Reject the call while the protocol or the curve is paused
+fn check_not_paused(config: &Config, bonding_curve: &BondingCurve) -> Result<()> {
+    require!(
+        !config.paused && !bonding_curve.paused,
+        TokenCreatorError::TradingPaused
+    );
+    Ok(())
+}
+
+/// Reject launch setting changes once the curve has traded or taken auction commits
+fn check_launch_unlocked(bonding_curve: &BondingCurve) -> Result<()> {
+    require!(
+        bonding_curve.total_supply == 0
+            && bonding_curve.auction_committed_sol == 0
+            && !bonding_curve.is_graduated,
+        TokenCreatorError::LaunchConfigLocked
+    );
+    Ok(())
+}
+
+/// Reject transactions that land after the caller's deadline
+fn check_deadline(deadline: Option<i64>, clock: &Clock) -> Result<()> {
+    if let Some(deadline) = deadline {
+        require!(clock.unix_timestamp <= deadline, TokenCreatorError::TransactionExpired);
+    }
+    Ok(())
+}
+
+/// Validate growth_rate based on curve type
+fn validate_curve_params(curve_type: CurveType, growth_rate: u64) -> Result<()> {
+    match curve_type {
+        CurveType::Linear => {
+            require!(growth_rate == 0, TokenCreatorError::InvalidAmount);
+        },
+        CurveType::Exponential => {
+            require!(growth_rate > 0 && growth_rate <= 10000, TokenCreatorError::InvalidAmount);
+        },
+        CurveType::Logarithmic => {
+            require!(
+                growth_rate > 0 && growth_rate <= MAX_LOGARITHMIC_GROWTH_RATE,
+                TokenCreatorError::InvalidAmount
+            );
+        },
+        CurveType::ConstantProduct => {
+            // growth_rate is the virtual token reserve as a multiple of max_supply
+            // (13530 = 1.353x, pump.fun's ratio); it must exceed 1x so the whole
+            // supply can be bought for a finite amount of SOL
+            require!(
+                growth_rate > 10000 && growth_rate <= MAX_VIRTUAL_RESERVE_RATIO,
+                TokenCreatorError::InvalidAmount
+            );
+        },
+        CurveType::Piecewise => {
+            // Stages are configured through initialize_multi_stage_bonding_curve
+            return err!(TokenCreatorError::InvalidCurveStages);
+        }
+    }
+
+    Ok(())
+}
+
+/// Check Config values against the protocol limits
+pub fn validate_config_params(params: &ConfigParams) -> Result<()> {
+    require!(
+        params.platform_treasury != Pubkey::default(),
+        TokenCreatorError::InvalidConfig
+    );
+    require!(params.graduation_threshold > 0, TokenCreatorError::InvalidConfig);
+    require!(params.creation_fee <= MAX_CREATION_FEE, TokenCreatorError::InvalidConfig);
+
+    // The transaction fee is split between the creator and the platform, and
+    // referrers are paid out of the platform's part
+    require!(
+        params.transaction_fee_bps <= MAX_TRANSACTION_FEE_BPS
+            && params.creator_fee_bps as u32 + params.platform_fee_bps as u32
+                == params.transaction_fee_bps as u32
+            && params.referral_fee_bps <= params.platform_fee_bps,
+        TokenCreatorError::InvalidConfig
+    );
+
+    require!(
+        (0..=MAX_LAUNCH_PROTECTION_PERIOD).contains(&params.launch_protection_period)
+            && params.transaction_cooldown <= MAX_TRANSACTION_COOLDOWN,
+        TokenCreatorError::InvalidConfig
+    );
+    require!(
+        (1..=10000).contains(&params.max_price_impact_bps)
+            && (1..=10000).contains(&params.max_sell_price_impact_bps),
+        TokenCreatorError::InvalidConfig
+    );
+
+    require!(
+        params.graduation_liquidity_bps as u32
+            + params.graduation_platform_bps as u32
+            + params.graduation_creator_bps as u32 == 10000,
+        TokenCreatorError::InvalidConfig
+    );
+    // A curve graduating at the threshold must have liquidity left for a DEX
+    // pool once the migration budget is held back
+    require!(
+        params.graduation_threshold as u128 * params.graduation_liquidity_bps as u128 / 10000
+            > DEX_MIGRATION_BUDGET as u128,
+        TokenCreatorError::InvalidConfig
+    );
+    require!(
+        params.keeper_reward <= MAX_KEEPER_REWARD,
+        TokenCreatorError::InvalidConfig
+    );
+    require!(
+        params.sniper_tax_start_bps <= MAX_SNIPER_TAX_BPS,
+        TokenCreatorError::InvalidConfig
+    );
+    require!(
+        params.max_wallet_buy_during_protection >= params.max_buy_during_protection
+            && params.max_wallet_holding_bps <= 10000,
+        TokenCreatorError::InvalidConfig
+    );
+    Ok(())
+}
+
+fn apply_config_params(config: &mut Config, params: &ConfigParams) {
+    config.platform_treasury = params.platform_treasury;
+    config.graduation_threshold = params.graduation_threshold;
+    config.creation_fee = params.creation_fee;
+    config.transaction_fee_bps = params.transaction_fee_bps;
+    config.creator_fee_bps = params.creator_fee_bps;
+    config.platform_fee_bps = params.platform_fee_bps;
+    config.launch_protection_period = params.launch_protection_period;
+    config.max_buy_during_protection = params.max_buy_during_protection;
+    config.transaction_cooldown = params.transaction_cooldown;
+    config.max_price_impact_bps = params.max_price_impact_bps;
+    config.max_sell_price_impact_bps = params.max_sell_price_impact_bps;
+    config.graduation_liquidity_bps = params.graduation_liquidity_bps;
+    config.graduation_platform_bps = params.graduation_platform_bps;
+    config.graduation_creator_bps = params.graduation_creator_bps;
+    config.keeper_reward = params.keeper_reward;
+    config.referral_fee_bps = params.referral_fee_bps;
+    config.sniper_tax_start_bps = params.sniper_tax_start_bps;
+    config.sniper_tax_recipient = params.sniper_tax_recipient;
+    config.max_wallet_buy_during_protection = params.max_wallet_buy_during_protection;
+    config.max_wallet_holding_bps = params.max_wallet_holding_bps;
+}
+
+/// Shared setup for every bonding curve initializer: defaults, treasury and creation fee
+fn setup_bonding_curve(
+    accounts: &mut InitializeBondingCurve,
+    bumps: &InitializeBondingCurveBumps,
+    initial_price: u64,
+    price_increment: u64,
+    max_supply: u64,
+    curve_type: CurveType,
+    growth_rate: u64,
+) -> Result<()> {
+    let bonding_curve = &mut accounts.bonding_curve;
+    let clock = Clock::get()?;
+
+    require!(accounts.mint.decimals <= 9, TokenCreatorError::InvalidAmount); // SPL Token max decimals
+
+    bonding_curve.mint = accounts.mint.key();
+    bonding_curve.decimals = accounts.mint.decimals;
+    bonding_curve.current_price = initial_price;
+    bonding_curve.initial_price = initial_price;
+    bonding_curve.price_increment = price_increment;
+    bonding_curve.total_supply = 0;
+    bonding_curve.max_supply = max_supply;
+    bonding_curve.authority = accounts.authority.key();
+    bonding_curve.bump = bumps.bonding_curve;
+    bonding_curve.curve_type = curve_type;
+    bonding_curve.growth_rate = growth_rate;
+    bonding_curve.treasury_balance = 0;
+    bonding_curve.curve_sol_balance = 0;
+    bonding_curve.total_volume = 0;
+    bonding_curve.is_graduated = false;
+
+    // Fees, graduation terms and anti-bot limits are fixed at creation from Config
+    let config = &accounts.config;
+    bonding_curve.graduation_threshold = config.graduation_threshold;
+    bonding_curve.creation_fee = config.creation_fee;
+    bonding_curve.transaction_fee_bps = config.transaction_fee_bps;
+    bonding_curve.creator_fee_bps = config.creator_fee_bps;
+    bonding_curve.platform_fee_bps = config.platform_fee_bps;
+    bonding_curve.referral_fee_bps = config.referral_fee_bps;
+    bonding_curve.graduation_liquidity_bps = config.graduation_liquidity_bps;
+    bonding_curve.graduation_platform_bps = config.graduation_platform_bps;
+    bonding_curve.graduation_creator_bps = config.graduation_creator_bps;
+    bonding_curve.migration_target = MigrationTarget::InHousePool;
+    bonding_curve.graduation_deadline = None;
+    bonding_curve.presale_merkle_root = None;
+    bonding_curve.presale_end = 0;
+    bonding_curve.opening_auction_end = 0;
+    bonding_curve.auction_committed_sol = 0;
+    bonding_curve.auction_filled_sol = 0;
+    bonding_curve.auction_token_amount = 0;
+    bonding_curve.auction_settled = false;
+    bonding_curve.paused = false;
+    bonding_curve.creator_fees_owed = 0;
+    bonding_curve.platform_fees_owed = 0;
+
+    // Anti-bot protection settings
+    bonding_curve.creation_timestamp = clock.unix_timestamp;
+    bonding_curve.launch_protection_period = config.launch_protection_period;
+    bonding_curve.max_buy_during_protection = config.max_buy_during_protection;
+    bonding_curve.transaction_cooldown = config.transaction_cooldown;
+    bonding_curve.max_price_impact_bps = config.max_price_impact_bps;
+    bonding_curve.max_sell_price_impact_bps = config.max_sell_price_impact_bps;
+    bonding_curve.sniper_tax_start_bps = config.sniper_tax_start_bps;
+    bonding_curve.sniper_tax_recipient = config.sniper_tax_recipient;
+    bonding_curve.max_wallet_buy_during_protection = config.max_wallet_buy_during_protection;
+    bonding_curve.max_wallet_holding_bps = config.max_wallet_holding_bps;
+
+    // Initialize treasury if needed
+    let treasury = &mut accounts.treasury;
+    if treasury.authority == Pubkey::default() {
+        treasury.authority = accounts.authority.key();
+        treasury.bump = bumps.treasury;
+    } else {
+        // Validate existing treasury owner to prevent hijacking
+        require!(
+            treasury.authority == accounts.authority.key(),
+            TokenCreatorError::InsufficientAuthority
+        );
+    }
+
+    // Charge creation fee
+    let cpi_context = CpiContext::new(
+        accounts.system_program.to_account_info(),
+        anchor_lang::system_program::Transfer {
+            from: accounts.authority.to_account_info(),
+            to: accounts.treasury.to_account_info(),
+        },
+    );
+    anchor_lang::system_program::transfer(cpi_context, bonding_curve.creation_fee)?;
+
+    // Update the on-chain treasury_balance to include the creation fee
+    bonding_curve.treasury_balance = bonding_curve
+        .treasury_balance
+        .saturating_add(bonding_curve.creation_fee);
+
+    // Update treasury total collected
+    let treasury = &mut accounts.treasury;
+    treasury.total_collected = treasury
+        .total_collected
+        .saturating_add(bonding_curve.creation_fee);
+
+    Ok(())
+}
+
+/// LaunchConfigured event with the curve's current launch settings
+fn launch_configured_event(bonding_curve: &BondingCurve) -> LaunchConfigured {
+    LaunchConfigured {
+        mint: bonding_curve.mint,
+        migration_target: bonding_curve.migration_target,
+        graduation_deadline: bonding_curve.graduation_deadline,
+        presale_merkle_root: bonding_curve.presale_merkle_root,
+        presale_end: bonding_curve.presale_end,
+        opening_auction_end: bonding_curve.opening_auction_end,
+    }
+}
+
+/// CurveInitialized event for a freshly set up curve
+fn curve_initialized_event(bonding_curve: &BondingCurve) -> CurveInitialized {
+    CurveInitialized {
+        mint: bonding_curve.mint,
+        creator: bonding_curve.authority,
+        curve_type: bonding_curve.curve_type,
+        initial_price: bonding_curve.initial_price,
+        price_increment: bonding_curve.price_increment,
+        growth_rate: bonding_curve.growth_rate,
+        max_supply: bonding_curve.max_supply,
+        stage_count: bonding_curve.stages.len() as u8,
+        graduation_threshold: bonding_curve.graduation_threshold,
+        creation_fee: bonding_curve.creation_fee,
+        transaction_fee_bps: bonding_curve.transaction_fee_bps,
+        timestamp: bonding_curve.creation_timestamp,
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()) @ TokenCreatorError::InsufficientAuthority
+    )]
+    pub program: Program<'info, crate::program::DegenieTokenCreator>,
+    
+    /// The admin must be the program's upgrade authority
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ TokenCreatorError::InsufficientAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ TokenCreatorError::InsufficientAuthority,
+    )]
+    pub config: Account<'info, Config>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PauseCurve<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ TokenCreatorError::InsufficientAuthority,
+    )]
+    pub config: Account<'info, Config>,
+    
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, symbol: String, uri: String, decimals: u8)]
+pub struct CreateToken<'info> {
+    #[account(
+        init,
+        payer = mint_authority,
+        mint::decimals = decimals,
+        mint::authority = mint_authority,
+    )]
+    pub mint: Account<'info, Mint>,
+
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = mint_authority,
+        associated_token::mint = mint,
+        associated_token::authority = mint_authority,
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub mint_authority: Signer<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub metadata_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MintTokens<'info> {
+    #[account(
+        mut,
+        constraint = mint.mint_authority == COption::Some(authority.key()) @ TokenCreatorError::InsufficientAuthority
+    )]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        constraint = destination.mint == mint.key() @ TokenCreatorError::InvalidAmount,
+        constraint = destination.owner == authority.key() @ TokenCreatorError::InsufficientAuthority
+    )]
+    pub destination: Account<'info, TokenAccount>,
+    
+    #[account(
+        constraint = authority.key() == mint.mint_authority.unwrap() @ TokenCreatorError::InsufficientAuthority
+    )]
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct BurnTokens<'info> {
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(mut)]
+    pub from: Account<'info, TokenAccount>,
+    
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct TransferTokens<'info> {
+    #[account(mut)]
+    pub from: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub to: Account<'info, TokenAccount>,
+    
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeTokenAccount<'info> {
+    #[account(mut)]
+    pub account: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ThawTokenAccount<'info> {
+    #[account(mut)]
+    pub account: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    pub authority: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeBondingCurve<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + BondingCurve::INIT_SPACE,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyTokens<'info> {
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = buyer
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + UserTracker::INIT_SPACE,
+        seeds = [b"user_tracker", mint.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub user_tracker: Account<'info, UserTracker>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    /// Optional referrer paid BondingCurve::referral_fee_bps of the fee
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer.wallet.as_ref()],
+        bump = referrer.bump
+    )]
+    pub referrer: Option<Account<'info, Referrer>>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SellTokens<'info> {
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    
+    #[account(mut)]
+    pub seller_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    /// Optional referrer paid BondingCurve::referral_fee_bps of the fee
+    #[account(
+        mut,
+        seeds = [b"referrer", referrer.wallet.as_ref()],
+        bump = referrer.bump
+    )]
+    pub referrer: Option<Account<'info, Referrer>>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GraduateToRaydium<'info> {
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    
+    /// Anyone may crank graduation once the threshold is met and earns
+    /// Config::keeper_reward
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateRaydiumPool<'info> {
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = keeper,
+        space = 8 + PoolState::INIT_SPACE,
+        seeds = [b"pool", mint.key().as_ref()],
+        bump
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(
+        init,
+        payer = keeper,
+        seeds = [b"pool_token_vault", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = pool_state,
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+    
+    /// SOL side of the pool, a lamport-only PDA
+    #[account(
+        mut,
+        seeds = [b"pool_sol_vault", mint.key().as_ref()],
+        bump
+    )]
+    pub sol_vault: SystemAccount<'info>,
+    
+    #[account(
+        init,
+        payer = keeper,
+        seeds = [b"lp_mint", mint.key().as_ref()],
+        bump,
+        mint::decimals = 9,
+        mint::authority = pool_state,
+    )]
+    pub lp_mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = keeper,
+        associated_token::mint = lp_mint,
+        associated_token::authority = treasury,
+    )]
+    pub treasury_lp_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: Creator account - receives the graduation bonus; must be the curve authority
+    #[account(
+        mut,
+        constraint = creator.key() == bonding_curve.authority @ TokenCreatorError::InsufficientAuthority
+    )]
+    pub creator: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    /// CHECK: Platform treasury for receiving the graduation fee
+    #[account(
+        mut,
+        address = config.platform_treasury @ TokenCreatorError::InvalidAmount
+    )]
+    pub platform_treasury: UncheckedAccount<'info>,
+    
+    /// Anyone may migrate a graduated curve and earns Config::keeper_reward
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureLaunch<'info> {
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump,
+        has_one = authority @ TokenCreatorError::InsufficientAuthority,
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRefund<'info> {
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = holder,
+    )]
+    pub holder_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CommitBuy<'info> {
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + AuctionCommit::INIT_SPACE,
+        seeds = [b"auction_commit", mint.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub auction_commit: Account<'info, AuctionCommit>,
+    
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + UserTracker::INIT_SPACE,
+        seeds = [b"user_tracker", mint.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub user_tracker: Account<'info, UserTracker>,
+    
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + AuctionEscrow::INIT_SPACE,
+        seeds = [b"auction_escrow", mint.key().as_ref()],
+        bump
+    )]
+    pub auction_escrow: Account<'info, AuctionEscrow>,
+    
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleOpeningAuction<'info> {
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    
+    /// Only exists once somebody has committed
+    #[account(
+        mut,
+        seeds = [b"auction_escrow", mint.key().as_ref()],
+        bump = auction_escrow.bump
+    )]
+    pub auction_escrow: Option<Account<'info, AuctionEscrow>>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    pub keeper: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAuctionFill<'info> {
+    #[account(
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        close = buyer,
+        seeds = [b"auction_commit", mint.key().as_ref(), buyer.key().as_ref()],
+        bump = auction_commit.bump
+    )]
+    pub auction_commit: Account<'info, AuctionCommit>,
+    
+    #[account(
+        mut,
+        seeds = [b"auction_escrow", mint.key().as_ref()],
+        bump = auction_escrow.bump
+    )]
+    pub auction_escrow: Account<'info, AuctionEscrow>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_tracker", mint.key().as_ref(), buyer.key().as_ref()],
+        bump = user_tracker.bump
+    )]
+    pub user_tracker: Account<'info, UserTracker>,
+    
+    /// CHECK: the wallet behind auction_commit, which its seeds pin down; only
+    /// receives lamports
+    #[account(mut)]
+    pub buyer: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = buyer,
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCreatorFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump,
+        has_one = authority @ TokenCreatorError::InsufficientAuthority,
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterReferrer<'info> {
+    #[account(
         init,
-        payer = mint_authority,
-        mint::decimals = 6,
-        mint::authority = mint_authority,
+        payer = wallet,
+        space = 8 + Referrer::INIT_SPACE,
+        seeds = [b"referrer", wallet.key().as_ref()],
+        bump
+    )]
+    pub referrer: Account<'info, Referrer>,
+    
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"referrer", wallet.key().as_ref()],
+        bump = referrer.bump,
+        has_one = wallet @ TokenCreatorError::InsufficientAuthority,
+    )]
+    pub referrer: Account<'info, Referrer>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPlatformFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    /// CHECK: Platform treasury receiving the fees
+    #[account(
+        mut,
+        address = config.platform_treasury @ TokenCreatorError::InvalidAmount
+    )]
+    pub platform_treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateToDex<'info> {
+    #[account(
+        mut,
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"treasury", mint.key().as_ref()],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    
+    /// Lamport-only PDA that creates the DEX pool and receives its LP tokens
+    #[account(
+        mut,
+        seeds = [b"migration_authority", mint.key().as_ref()],
+        bump
+    )]
+    pub migration_authority: SystemAccount<'info>,
+    
+    #[account(
+        init,
+        payer = keeper,
+        associated_token::mint = mint,
+        associated_token::authority = migration_authority,
+    )]
+    pub migration_token_account: Account<'info, TokenAccount>,
+    
+    #[account(address = anchor_spl::token::spl_token::native_mint::ID)]
+    pub native_mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = keeper,
+        associated_token::mint = native_mint,
+        associated_token::authority = migration_authority,
+    )]
+    pub migration_wsol_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: Creator account - receives the graduation bonus; must be the curve authority
+    #[account(
+        mut,
+        constraint = creator.key() == bonding_curve.authority @ TokenCreatorError::InsufficientAuthority
+    )]
+    pub creator: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    /// CHECK: Platform treasury for receiving the graduation fee
+    #[account(
+        mut,
+        address = config.platform_treasury @ TokenCreatorError::InvalidAmount
+    )]
+    pub platform_treasury: UncheckedAccount<'info>,
+    
+    /// CHECK: Must be the program of the curve's migration target
+    pub dex_program: UncheckedAccount<'info>,
+    
+    /// Anyone may migrate a graduated curve and earns Config::keeper_reward.
+    /// Fronts the liquidity share, which the treasury repays in full.
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SwapOnPool<'info> {
+    #[account(
+        mut,
+        seeds = [b"pool", mint.key().as_ref()],
+        bump = pool_state.bump,
+        has_one = mint,
+        has_one = token_vault,
+        has_one = sol_vault,
+    )]
+    pub pool_state: Account<'info, PoolState>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(mut)]
+    pub token_vault: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"pool_sol_vault", mint.key().as_ref()],
+        bump = pool_state.sol_vault_bump
+    )]
+    pub sol_vault: SystemAccount<'info>,
+    
+    #[account(
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
+    )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct QuoteTrade<'info> {
+    #[account(
+        seeds = [b"bonding_curve", mint.key().as_ref()],
+        bump = bonding_curve.bump
     )]
+    pub bonding_curve: Account<'info, BondingCurve>,
+    
     pub mint: Account<'info, Mint>,
+}
+
+/// Protocol-wide settings, a singleton PDA at ["config"]
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey, // May update the config
+    pub bump: u8,
+    pub platform_treasury: Pubkey, // Receives platform fees and graduation shares
+    pub graduation_threshold: u64, // Market cap in lamports needed to graduate
+    pub creation_fee: u64,
+    pub transaction_fee_bps: u16,
+    pub creator_fee_bps: u16,
+    pub platform_fee_bps: u16,
+    // Anti-bot defaults for new curves
+    pub launch_protection_period: i64,
+    pub max_buy_during_protection: u64,
+    pub transaction_cooldown: u64,
+    pub max_price_impact_bps: u16,
+    pub max_sell_price_impact_bps: u16,
+    // Graduation split of the treasury in basis points (sums to 10000)
+    pub graduation_liquidity_bps: u16,
+    pub graduation_platform_bps: u16,
+    pub graduation_creator_bps: u16,
+    pub keeper_reward: u64, // Lamports the treasury pays per graduation and per migration
+    pub paused: bool, // Halts trading, graduation and migration on every curve
+    pub referral_fee_bps: u16, // Referrer's cut of the transaction fee, taken from the platform's
+    // Sniper tax defaults for new curves
+    pub sniper_tax_start_bps: u16,
+    pub sniper_tax_recipient: SniperTaxRecipient,
+    // Per-wallet limit defaults over the whole protection period
+    pub max_wallet_buy_during_protection: u64,
+    pub max_wallet_holding_bps: u16,
+}
+
+/// Settings passed to initialize_config and update_config
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub struct ConfigParams {
+    pub platform_treasury: Pubkey,
+    pub graduation_threshold: u64,
+    pub creation_fee: u64,
+    pub transaction_fee_bps: u16,
+    pub creator_fee_bps: u16,
+    pub platform_fee_bps: u16,
+    pub launch_protection_period: i64,
+    pub max_buy_during_protection: u64,
+    pub transaction_cooldown: u64,
+    pub max_price_impact_bps: u16,
+    pub max_sell_price_impact_bps: u16,
+    pub graduation_liquidity_bps: u16,
+    pub graduation_platform_bps: u16,
+    pub graduation_creator_bps: u16,
+    pub keeper_reward: u64,
+    pub referral_fee_bps: u16,
+    pub sniper_tax_start_bps: u16,
+    pub sniper_tax_recipient: SniperTaxRecipient,
+    pub max_wallet_buy_during_protection: u64,
+    pub max_wallet_holding_bps: u16,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct BondingCurve {
+    pub mint: Pubkey,
+    pub current_price: u64,
+    pub price_increment: u64,
+    pub total_supply: u64,
+    pub max_supply: u64,
+    pub authority: Pubkey,
+    pub bump: u8,
+    // New fields for enhanced bonding curve
+    pub initial_price: u64,
+    pub curve_type: CurveType,
+    pub growth_rate: u64, // Basis points (10000 = 100%)
+    pub treasury_balance: u64,
+    pub total_volume: u64,
+    pub graduation_threshold: u64, // Copied from Config at creation
+    pub is_graduated: bool,
+    pub creation_fee: u64,
+    pub transaction_fee_bps: u16, // Basis points (100 = 1%)
+    pub creator_fee_bps: u16,
+    pub platform_fee_bps: u16,
+    // Anti-bot protection fields
+    pub creation_timestamp: i64,
+    pub launch_protection_period: i64, // Duration in seconds (e.g., 3600 for 1 hour)
+    pub max_buy_during_protection: u64, // Max SOL per buy during protection period
+    pub transaction_cooldown: u64, // Minimum seconds between transactions per wallet
+    pub max_price_impact_bps: u16, // Maximum price impact in basis points (500 = 5%)
+    // Constant-product curve state (unused by the other curve types)
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    // Multi-stage curve segments, ordered by supply (empty for single-stage curves)
+    #[max_len(MAX_CURVE_STAGES)]
+    pub stages: Vec<CurveStage>,
+    pub max_sell_price_impact_bps: u16, // Maximum price drop per sell in basis points
+    pub decimals: u8, // Mint decimals, used to convert raw amounts to curve units
+    // Graduation split of the treasury in basis points (sums to 10000)
+    pub graduation_liquidity_bps: u16,
+    pub graduation_platform_bps: u16,
+    pub graduation_creator_bps: u16,
+    pub migration_target: MigrationTarget, // Where liquidity goes at graduation
+    pub is_migrated: bool, // Liquidity has left the treasury for a pool
+    // Graduation payouts from the treasury, recorded for auditing
+    pub graduation_platform_payout: u64,
+    pub graduation_creator_payout: u64,
+    pub graduation_keeper_reward: u64, // Graduation and migration keeper rewards together
+    pub graduation_deadline: Option<i64>, // Past this without graduating, the curve only allows refunds
+    pub paused: bool, // Set by the Config admin to halt everything but refunds and auction claims
+    // Trading fees held in the treasury until claimed
+    pub creator_fees_owed: u64,
+    pub platform_fees_owed: u64,
+    pub referral_fee_bps: u16, // Part of platform_fee_bps paid to the trade's referrer, if any
+    // Total buy fee at creation in basis points, decaying linearly to
+    // transaction_fee_bps over launch_protection_period
+    pub sniper_tax_start_bps: u16,
+    pub sniper_tax_recipient: SniperTaxRecipient,
+    pub max_wallet_buy_during_protection: u64, // Max total SOL per wallet during protection period
+    pub max_wallet_holding_bps: u16, // Max share of max_supply held per wallet then, 0 for no cap
+    // Allowlist of (wallet, allocation) leaves; only they may buy until presale_end
+    pub presale_merkle_root: Option<[u8; 32]>,
+    pub presale_end: i64,
+    // Opening batch auction: commits taken until opening_auction_end, then
+    // filled together by settle_opening_auction before trading opens
+    pub opening_auction_end: i64, // 0 when the curve opens straight to trading
+    pub auction_committed_sol: u64,
+    pub auction_filled_sol: u64, // Less than committed when max_supply cut the fill short
+    pub auction_token_amount: u64, // Curve units bought for all commits together
+    pub auction_settled: bool,
+    pub curve_sol_balance: u64, // Lamports backing the sold supply; refunds are paid from it
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PoolState {
+    pub is_initialized: bool,
+    pub mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub token_vault: Pubkey,
+    pub sol_vault: Pubkey,
+    pub creation_timestamp: i64,
+    pub bump: u8,
+    pub sol_vault_bump: u8,
+    pub sol_reserves: u64, // Lamports held by sol_vault for trading
+    pub token_reserves: u64, // Raw tokens held by token_vault
+    pub lp_supply: u64, // LP tokens minted for the initial deposit
+    pub lp_burned: u64, // LP tokens burned at creation; equal to lp_supply once liquidity is permanent
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct UserTracker {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub last_transaction_time: i64,
+    pub total_bought_sol: u64,
+    pub transaction_count: u32,
+    pub bump: u8,
+    pub presale_bought_sol: u64, // Lamports spent against the presale allocation
+    pub tokens_bought: u64, // Raw tokens bought from the curve, which the holding cap counts
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    pub authority: Pubkey,
+    pub total_collected: u64,
+    pub bump: u8,
+}
+
+/// A buyer's SOL committed to a curve's opening auction, a PDA at
+/// ["auction_commit", mint, buyer]
+#[account]
+#[derive(InitSpace)]
+pub struct AuctionCommit {
+    pub mint: Pubkey,
+    pub buyer: Pubkey,
+    pub sol_amount: u64,
+    pub bump: u8,
+}
+
+/// Holds a curve's opening auction commits until they are filled or
+/// refunded, a PDA at ["auction_escrow", mint]
+#[account]
+#[derive(InitSpace)]
+pub struct AuctionEscrow {
+    pub mint: Pubkey,
+    pub bump: u8,
+}
+
+/// A referrer's earnings across all curves, a PDA at ["referrer", wallet]
+#[account]
+#[derive(InitSpace)]
+pub struct Referrer {
+    pub wallet: Pubkey, // Receives the earnings on claim
+    pub fees_owed: u64, // Lamports held by this account until claimed
+    pub total_earned: u64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug, InitSpace)]
+pub enum CurveType {
+    Linear,
+    Exponential,
+    Logarithmic,
+    ConstantProduct, // pump.fun-style x*y=k over virtual reserves
+    Piecewise, // multi-stage curve described by BondingCurve::stages
+}
+
+/// Who receives the sniper tax charged on buys during the launch window
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug, InitSpace)]
+pub enum SniperTaxRecipient {
+    Treasury, // Stays in the curve's treasury: deepens graduation liquidity or backs refunds
+    Creator, // Added to creator_fees_owed
+}
+
+/// Where a curve's liquidity goes at graduation, chosen by the creator at launch
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug, InitSpace)]
+pub enum MigrationTarget {
+    InHousePool, // create_raydium_pool's constant-product pool
+    RaydiumAmmV4, // Raydium AMM v4 initialize2, paired with an OpenBook market
+    RaydiumCpmm, // Raydium CP-Swap initialize
+}
+
+impl MigrationTarget {
+    /// Program migrate_to_dex must invoke; None for the in-house pool
+    pub fn program_id(&self) -> Option<Pubkey> {
+        match self {
+            MigrationTarget::InHousePool => None,
+            MigrationTarget::RaydiumAmmV4 => Some(RAYDIUM_AMM_V4_PROGRAM_ID),
+            MigrationTarget::RaydiumCpmm => Some(RAYDIUM_CPMM_PROGRAM_ID),
+        }
+    }
+
+    /// Positions of the accounts migrate_to_dex checks within the DEX's
+    /// pool-creation account list; None for the in-house pool
+    pub fn dex_account_layout(&self, mint: &Pubkey) -> Option<DexAccountLayout> {
+        match self {
+            MigrationTarget::InHousePool => None,
+            // token_program, ata_program, system_program, rent, amm, amm_authority,
+            // open_orders, lp_mint, coin_mint, pc_mint, coin_vault, pc_vault,
+            // target_orders, amm_config, create_fee_destination, market_program,
+            // market, user_wallet, user_token_coin, user_token_pc, user_token_lp
+            MigrationTarget::RaydiumAmmV4 => Some(DexAccountLayout {
+                account_count: 21,
+                payer: 17,
+                token_mint: 8,
+                sol_mint: 9,
+                token_account: 18,
+                sol_account: 19,
+                lp_mint: 7,
+                lp_account: 20,
+            }),
+            // creator, amm_config, authority, pool_state, token_0_mint, token_1_mint,
+            // lp_mint, creator_token_0, creator_token_1, creator_lp_token,
+            // token_0_vault, token_1_vault, create_pool_fee, observation_state,
+            // token_program, token_0_program, token_1_program,
+            // associated_token_program, system_program, rent
+            MigrationTarget::RaydiumCpmm => {
+                // CP-Swap orders the pair by mint address
+                let token_first = cpmm_token_first(mint);
+                let (token_index, sol_index) = if token_first { (0, 1) } else { (1, 0) };
+                Some(DexAccountLayout {
+                    account_count: 20,
+                    payer: 0,
+                    token_mint: 4 + token_index,
+                    sol_mint: 4 + sol_index,
+                    token_account: 7 + token_index,
+                    sol_account: 7 + sol_index,
+                    lp_mint: 6,
+                    lp_account: 9,
+                })
+            }
+        }
+    }
+}
+
+/// Indices into a DEX's pool-creation account list
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DexAccountLayout {
+    pub account_count: usize,
+    pub payer: usize, // Signs and pays for the pool; must be the migration authority
+    pub token_mint: usize,
+    pub sol_mint: usize, // Wrapped SOL mint
+    pub token_account: usize, // Migration authority's token deposit
+    pub sol_account: usize, // Migration authority's wrapped SOL deposit
+    pub lp_mint: usize,
+    pub lp_account: usize, // Receives the LP tokens migrate_to_dex burns
+}
+
+/// Simulated trade returned by quote_buy and quote_sell
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub struct TradeQuote {
+    pub token_amount: u64, // Raw tokens minted (buy) or burned (sell)
+    pub sol_amount: u64, // Lamports paid including fee (buy) or received after fee (sell)
+    pub transaction_fee: u64,
+    pub creator_fee: u64,
+    pub platform_fee: u64,
+    pub new_price: u64, // Spot price after the trade
+    pub price_impact_bps: u16,
+    pub sniper_tax: u64, // Launch-window surcharge on buys
+}
+
+/// Creator-supplied description of one stage of a multi-stage curve
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub struct CurveStageParams {
+    pub curve_type: CurveType,
+    pub end_supply: u64, // Supply at which the next stage takes over
+    pub growth_rate: u64, // Same meaning as in initialize_bonding_curve
+    pub price_increment: u64, // Linear stages only
+}
+
+/// A wallet's presale allowlist entry and its Merkle proof against
+/// BondingCurve::presale_merkle_root
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Debug)]
+pub struct PresaleProof {
+    pub allocation: u64, // Lamports the wallet may spend during the presale
+    pub proof: Vec<[u8; 32]>, // Sibling hashes from the leaf up to the root
+}
+
+/// One segment of a multi-stage curve as stored on-chain
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, InitSpace)]
+pub struct CurveStage {
+    pub curve_type: CurveType,
+    pub start_supply: u64,
+    pub end_supply: u64,
+    pub start_price: u64, // Closing price of the previous stage
+    pub start_cost: u128, // Lamports needed to buy the curve up to start_supply
+    pub growth_rate: u64,
+    pub price_increment: u64,
+}
+
+/// Direction of a curve trade or pool swap
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Debug)]
+pub enum TradeSide {
+    Buy, // SOL in, tokens out
+    Sell, // Tokens in, SOL out
+}
+
+// Events for indexers. Token amounts are raw unless noted, SOL amounts in lamports.
+
+/// Config created or changed; `admin` is the admin after the change
+#[event]
+pub struct ConfigUpdated {
+    pub admin: Pubkey,
+    pub params: ConfigParams,
+}
+
+/// Protocol-wide (`mint` None) or per-curve pause switched
+#[event]
+pub struct PauseUpdated {
+    pub mint: Option<Pubkey>,
+    pub paused: bool,
+}
+
+#[event]
+pub struct TokenCreated {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub decimals: u8,
+    pub initial_supply: u64,
+}
+
+#[event]
+pub struct TokenAccountFrozen {
+    pub mint: Pubkey,
+    pub account: Pubkey,
+}
+
+#[event]
+pub struct TokenAccountThawed {
+    pub mint: Pubkey,
+    pub account: Pubkey,
+}
+
+/// Prices are lamports per curve unit and `max_supply` is in curve units
+#[event]
+pub struct CurveInitialized {
+    pub mint: Pubkey,
+    pub creator: Pubkey,
+    pub curve_type: CurveType,
+    pub initial_price: u64,
+    pub price_increment: u64,
+    pub growth_rate: u64,
+    pub max_supply: u64,
+    pub stage_count: u8, // 0 for single-stage curves
+    pub graduation_threshold: u64,
+    pub creation_fee: u64,
+    pub transaction_fee_bps: u16,
+    pub timestamp: i64,
+}
+
+/// Migration target, graduation deadline, presale or opening auction set by the creator
+#[event]
+pub struct LaunchConfigured {
+    pub mint: Pubkey,
+    pub migration_target: MigrationTarget,
+    pub graduation_deadline: Option<i64>,
+    pub presale_merkle_root: Option<[u8; 32]>,
+    pub presale_end: i64,
+    pub opening_auction_end: i64,
+}
+
+/// A buy or sell on the bonding curve
+#[event]
+pub struct Trade {
+    pub mint: Pubkey,
+    pub user: Pubkey,
+    pub side: TradeSide,
+    pub token_amount: u64, // Minted (buy) or burned (sell)
+    pub sol_amount: u64, // Paid including fee (buy) or received after fee (sell)
+    pub transaction_fee: u64,
+    pub creator_fee: u64,
+    pub platform_fee: u64, // Net of referral_fee
+    pub referrer: Option<Pubkey>, // Referrer's wallet
+    pub referral_fee: u64,
+    pub sniper_tax: u64, // Launch-window surcharge on buys
+    pub new_price: u64, // Spot price after the trade, lamports per curve unit
+    pub new_supply: u64, // Curve units
+    pub treasury_balance: u64,
+    pub timestamp: i64,
+}
+
+/// Curve crossed its graduation threshold; `keeper` sent the transaction
+#[event]
+pub struct Graduated {
+    pub mint: Pubkey,
+    pub market_cap: u64,
+    pub liquidity_amount: u64, // Treasury share headed for the pool
+    pub keeper: Pubkey,
+    pub keeper_reward: u64,
+    pub timestamp: i64,
+}
+
+/// Graduated liquidity deposited into a pool and its LP tokens burned
+#[event]
+pub struct PoolCreated {
+    pub mint: Pubkey,
+    pub migration_target: MigrationTarget,
+    pub lp_mint: Pubkey,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    pub lp_burned: u64,
+    pub platform_payout: u64,
+    pub creator_payout: u64,
+    pub keeper: Pubkey,
+    pub keeper_reward: u64,
+    pub timestamp: i64,
+}
+
+/// A swap against the in-house pool
+#[event]
+pub struct PoolSwap {
+    pub mint: Pubkey,
+    pub user: Pubkey,
+    pub side: TradeSide,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub sol_reserves: u64, // After the swap
+    pub token_reserves: u64,
+}
+
+/// SOL committed to a curve's opening auction
+#[event]
+pub struct AuctionCommitted {
+    pub mint: Pubkey,
+    pub buyer: Pubkey,
+    pub sol_amount: u64,
+    pub buyer_committed_sol: u64, // The buyer's total across commits
+    pub auction_committed_sol: u64,
+    pub timestamp: i64,
+}
+
+/// Opening auction filled as one buy; prices are lamports per curve unit
+#[event]
+pub struct OpeningAuctionSettled {
+    pub mint: Pubkey,
+    pub committed_sol: u64,
+    pub filled_sol: u64,
+    pub transaction_fee: u64,
+    pub token_amount: u64,
+    pub new_price: u64,
+    pub new_supply: u64, // Curve units
+    pub treasury_balance: u64,
+    pub timestamp: i64,
+}
+
+/// A buyer's tokens and unfilled SOL from a settled opening auction
+#[event]
+pub struct AuctionFillClaimed {
+    pub mint: Pubkey,
+    pub buyer: Pubkey,
+    pub token_amount: u64,
+    pub refund_amount: u64,
+}
+
+/// Tokens burned for a refund after a missed graduation deadline
+#[event]
+pub struct Refunded {
+    pub mint: Pubkey,
+    pub holder: Pubkey,
+    pub token_amount: u64,
+    pub refund_amount: u64,
+    pub remaining_supply: u64, // Curve units
+    pub treasury_balance: u64,
+}
+
+/// Accrued trading fees paid out of the treasury
+#[event]
+pub struct FeesClaimed {
+    pub mint: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+/// Referral earnings paid out of a Referrer account
+#[event]
+pub struct ReferralFeesClaimed {
+    pub wallet: Pubkey,
+    pub amount: u64,
+}
+
+// Enhanced bonding curve calculation functions
+pub fn calculate_price_exponential(
+    initial_price: u64,
+    total_supply: u64,
+    growth_rate: u64, // in basis points
+) -> Result<u64> {
+    // Exponential curve: price = initial_price * (1 + growth_rate/10000) ^ (supply / scale)
+    // Using fixed-point arithmetic to avoid floating point
+    
+    if total_supply == 0 {
+        return Ok(initial_price);
+    }
+    
+    // Scale factor to control growth rate
+    let supply_scaled = total_supply / EXPONENTIAL_SCALE_FACTOR;
+    
+    // Calculate (1 + growth_rate/10000)^supply_scaled using fast exponentiation
+    let growth_multiplier = 10000u128 + growth_rate as u128; // e.g., 10100 for 1% growth
+    
+    // Use u128 for intermediate calculations to prevent overflow
+    let mut result = 10000u128; // Start with 1.0 in fixed point
+    let mut base = growth_multiplier;
+    let mut exp = supply_scaled;
+    
+    // Fast exponentiation by squaring - O(log n) instead of O(n)
+    while exp > 0 {
+        if exp & 1 == 1 {
+            // If exp is odd, multiply result by base
+            result = result
+                .checked_mul(base)
+                .ok_or(TokenCreatorError::InvalidAmount)?
+                .checked_div(10000)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+        }
+        
+        // Square the base
+        base = base
+            .checked_mul(base)
+            .ok_or(TokenCreatorError::InvalidAmount)?
+            .checked_div(10000)
+            .ok_or(TokenCreatorError::InvalidAmount)?;
+        
+        exp >>= 1; // Divide exp by 2
+    }
+    
+    // Apply result to initial price and convert back to u64
+    let price_u128 = (initial_price as u128)
+        .checked_mul(result)
+        .ok_or(TokenCreatorError::InvalidAmount)?
+        .checked_div(10000)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    
+    // Ensure result fits in u64
+    if price_u128 > u64::MAX as u128 {
+        return Err(TokenCreatorError::InvalidAmount);
+    }
+    
+    Ok(price_u128 as u64)
+}
 
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
-    pub metadata: UncheckedAccount<'info>,
+/// Fixed-point precision of the curve integrals (1.0 = 10^12)
+const CURVE_PRECISION: u128 = 1_000_000_000_000;
+/// Tokens per compounding step of the exponential curve
+const EXPONENTIAL_SCALE_FACTOR: u64 = 1000;
 
-    #[account(
-        init,
-        payer = mint_authority,
-        associated_token::mint = mint,
-        associated_token::authority = mint_authority,
-    )]
-    pub token_account: Account<'info, TokenAccount>,
+/// Total lamports needed to take the exponential curve from zero to `supply`.
+///
+/// The price compounds by `growth_rate` every `EXPONENTIAL_SCALE_FACTOR`
+/// tokens, so the cost up to step `k` is the geometric series
+/// `initial_price * scale * (r^k - 1) / (r - 1)`. Inside a step the cost is
+/// interpolated linearly between the two step boundaries, which keeps the
+/// integral monotonic and makes the cost of any range `F(b) - F(a)`.
+pub fn calculate_exponential_integral(
+    initial_price: u64,
+    supply: u64,
+    growth_rate: u64, // in basis points
+) -> Result<u128> {
+    exponential_integral(initial_price, supply, growth_rate)
+        .ok_or_else(|| error!(TokenCreatorError::InvalidAmount))
+}
 
-    #[account(mut)]
-    pub mint_authority: Signer<'info>,
+fn exponential_integral(initial_price: u64, supply: u64, growth_rate: u64) -> Option<u128> {
+    let step = supply / EXPONENTIAL_SCALE_FACTOR;
+    let remainder = supply % EXPONENTIAL_SCALE_FACTOR;
 
-    pub rent: Sysvar<'info, Rent>,
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub metadata_program: UncheckedAccount<'info>,
+    let lower = exponential_step_cost(initial_price, step, growth_rate)?;
+    if remainder == 0 {
+        return Some(lower);
+    }
+
+    let upper = exponential_step_cost(initial_price, step.checked_add(1)?, growth_rate)?;
+    upper
+        .checked_sub(lower)?
+        .checked_mul(remainder as u128)?
+        .checked_div(EXPONENTIAL_SCALE_FACTOR as u128)?
+        .checked_add(lower)
 }
 
-#[derive(Accounts)]
-pub struct MintTokens<'info> {
-    #[account(
-        mut,
-        constraint = mint.mint_authority == COption::Some(authority.key()) @ TokenCreatorError::InsufficientAuthority
-    )]
-    pub mint: Account<'info, Mint>,
-    
-    #[account(
-        mut,
-        constraint = destination.mint == mint.key() @ TokenCreatorError::InvalidAmount,
-        constraint = destination.owner == authority.key() @ TokenCreatorError::InsufficientAuthority
-    )]
-    pub destination: Account<'info, TokenAccount>,
-    
-    #[account(
-        constraint = authority.key() == mint.mint_authority.unwrap() @ TokenCreatorError::InsufficientAuthority
-    )]
-    pub authority: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+/// Cost of the first `steps` full steps: price * scale * (r^steps - 1) / (r - 1)
+fn exponential_step_cost(initial_price: u64, steps: u64, growth_rate: u64) -> Option<u128> {
+    if growth_rate == 0 {
+        return (initial_price as u128)
+            .checked_mul(EXPONENTIAL_SCALE_FACTOR as u128)?
+            .checked_mul(steps as u128);
+    }
+
+    // r - 1 = growth_rate / 10000, expressed at CURVE_PRECISION
+    let rate = (growth_rate as u128).checked_mul(CURVE_PRECISION / 10000)?;
+    let growth = exponential_growth_factor(steps, rate)?;
+
+    growth
+        .checked_sub(CURVE_PRECISION)?
+        .checked_mul(initial_price as u128)?
+        .checked_mul(EXPONENTIAL_SCALE_FACTOR as u128)?
+        .checked_div(rate)
+}
+
+/// (1 + rate)^steps at CURVE_PRECISION using exponentiation by squaring
+fn exponential_growth_factor(steps: u64, rate: u128) -> Option<u128> {
+    let mut result = CURVE_PRECISION;
+    let mut base = CURVE_PRECISION.checked_add(rate)?;
+    let mut exp = steps;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result.checked_mul(base)?.checked_div(CURVE_PRECISION)?;
+        }
+        exp >>= 1;
+        // Skip the final squaring so large bases don't overflow needlessly
+        if exp > 0 {
+            base = base.checked_mul(base)?.checked_div(CURVE_PRECISION)?;
+        }
+    }
+
+    Some(result)
+}
+
+/// Tokens per unit of the logarithmic curve's argument
+const LOGARITHMIC_SCALE_FACTOR: u64 = 1000;
+/// ln(2) at CURVE_PRECISION
+const LN_2: u128 = 693_147_180_560;
+
+/// Logarithmic curve: price = initial_price * (1 + growth_rate/10000 * ln(1 + supply / scale))
+///
+/// Price rises quickly at launch and flattens as supply grows. `growth_rate`
+/// is the price gained, relative to the initial price, per unit of ln.
+pub fn calculate_price_logarithmic(
+    initial_price: u64,
+    total_supply: u64,
+    growth_rate: u64, // in basis points
+) -> Result<u64> {
+    let scale = LOGARITHMIC_SCALE_FACTOR as u128;
+    let ln_growth = ln_ratio(scale + total_supply as u128, scale)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+
+    let price_increase = (initial_price as u128)
+        .checked_mul(growth_rate as u128)
+        .and_then(|value| value.checked_mul(ln_growth))
+        .and_then(|value| value.checked_div(10000 * CURVE_PRECISION))
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+
+    let price = price_increase
+        .checked_add(initial_price as u128)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    u64::try_from(price).map_err(|_| error!(TokenCreatorError::InvalidAmount))
+}
+
+/// Total lamports needed to take the logarithmic curve from zero to `supply`.
+///
+/// Closed form of the price integral:
+/// `initial_price * (s + growth_rate/10000 * ((scale + s) * ln(1 + s/scale) - s))`
+pub fn calculate_logarithmic_integral(
+    initial_price: u64,
+    supply: u64,
+    growth_rate: u64, // in basis points
+) -> Result<u128> {
+    logarithmic_integral(initial_price, supply, growth_rate)
+        .ok_or_else(|| error!(TokenCreatorError::InvalidAmount))
+}
+
+fn logarithmic_integral(initial_price: u64, supply: u64, growth_rate: u64) -> Option<u128> {
+    let scale = LOGARITHMIC_SCALE_FACTOR as u128;
+    let supply = supply as u128;
+    let ln_growth = ln_ratio(scale + supply, scale)?;
+
+    // (scale + s) * ln(1 + s/scale) - s, kept at CURVE_PRECISION. Truncation in
+    // ln can dip just below s for tiny supplies, where the true value is ~0.
+    let curvature = (scale + supply)
+        .checked_mul(ln_growth)?
+        .saturating_sub(supply.checked_mul(CURVE_PRECISION)?);
+
+    let log_cost = curvature
+        .checked_mul(initial_price as u128)?
+        .checked_div(CURVE_PRECISION)?
+        .checked_mul(growth_rate as u128)?
+        .checked_div(10000)?;
+
+    (initial_price as u128)
+        .checked_mul(supply)?
+        .checked_add(log_cost)
+}
+
+/// ln(numerator / denominator) at CURVE_PRECISION for ratios >= 1
+fn ln_ratio(numerator: u128, denominator: u128) -> Option<u128> {
+    if denominator == 0 || numerator < denominator {
+        return None;
+    }
+
+    // Integer part of log2 from the bit length of the quotient
+    let integer_bits = 127 - (numerator / denominator).leading_zeros();
+    let mut value = numerator
+        .checked_mul(CURVE_PRECISION)?
+        .checked_div(denominator.checked_shl(integer_bits)?)?;
+
+    // Fractional bits by repeated squaring of a value in [1, 2)
+    let mut log2 = (integer_bits as u128) * CURVE_PRECISION;
+    let mut bit = CURVE_PRECISION / 2;
+    while bit > 0 {
+        value = value.checked_mul(value)? / CURVE_PRECISION;
+        if value >= 2 * CURVE_PRECISION {
+            value /= 2;
+            log2 += bit;
+        }
+        bit /= 2;
+    }
+
+    Some(log2 * LN_2 / CURVE_PRECISION)
+}
+
+/// Validate creator-supplied stages and precompute where each one starts.
+///
+/// Every stage opens at the closing price of the previous stage, so the
+/// price is continuous across breakpoints and no trade can jump a gap.
+pub fn build_curve_stages(
+    initial_price: u64,
+    params: &[CurveStageParams],
+) -> Result<Vec<CurveStage>> {
+    require!(
+        !params.is_empty() && params.len() <= MAX_CURVE_STAGES,
+        TokenCreatorError::InvalidCurveStages
+    );
+
+    let mut stages = Vec::with_capacity(params.len());
+    let mut start_supply = 0u64;
+    let mut start_price = initial_price;
+    let mut start_cost = 0u128;
+
+    for param in params {
+        match param.curve_type {
+            CurveType::Linear => {
+                require!(param.price_increment > 0, TokenCreatorError::InvalidCurveStages);
+            },
+            CurveType::Exponential | CurveType::Logarithmic => {},
+            CurveType::ConstantProduct | CurveType::Piecewise => {
+                return err!(TokenCreatorError::InvalidCurveStages);
+            }
+        }
+        validate_curve_params(param.curve_type, param.growth_rate)?;
+        require!(param.end_supply > start_supply, TokenCreatorError::InvalidCurveStages);
+
+        let stage = CurveStage {
+            curve_type: param.curve_type,
+            start_supply,
+            end_supply: param.end_supply,
+            start_price,
+            start_cost,
+            growth_rate: param.growth_rate,
+            price_increment: param.price_increment,
+        };
+
+        let stage_length = param.end_supply - start_supply;
+        start_price = stage_price(&stage, stage_length)?;
+        start_cost = stage_integral(&stage, stage_length)
+            .and_then(|cost| cost.checked_add(start_cost))
+            .ok_or(TokenCreatorError::InvalidCurveStages)?;
+        start_supply = param.end_supply;
+        stages.push(stage);
+    }
+
+    Ok(stages)
+}
+
+/// Spot price of a multi-stage curve at `supply`
+pub fn calculate_price_piecewise(bonding_curve: &BondingCurve, supply: u64) -> Result<u64> {
+    let stage = find_curve_stage(bonding_curve, supply)?;
+    stage_price(stage, supply - stage.start_supply)
+}
+
+/// Total lamports needed to take a multi-stage curve from zero to `supply`
+fn piecewise_integral(bonding_curve: &BondingCurve, supply: u64) -> Option<u128> {
+    let stage = find_curve_stage(bonding_curve, supply).ok()?;
+    stage_integral(stage, supply - stage.start_supply)?.checked_add(stage.start_cost)
+}
+
+/// Stage containing `supply`; the last stage extends past its breakpoint so
+/// oversized buys still surface as ExceedsMaxSupply
+fn find_curve_stage(bonding_curve: &BondingCurve, supply: u64) -> Result<&CurveStage> {
+    bonding_curve.stages
+        .iter()
+        .find(|stage| supply < stage.end_supply)
+        .or_else(|| bonding_curve.stages.last())
+        .ok_or_else(|| error!(TokenCreatorError::InvalidCurveStages))
+}
+
+fn stage_price(stage: &CurveStage, stage_supply: u64) -> Result<u64> {
+    match stage.curve_type {
+        CurveType::Linear => {
+            let price_increase = (stage.price_increment as u128)
+                .checked_mul(stage_supply as u128)
+                .ok_or(TokenCreatorError::InvalidAmount)?
+                / 1000;
+            let price = price_increase + stage.start_price as u128;
+            u64::try_from(price).map_err(|_| error!(TokenCreatorError::InvalidAmount))
+        },
+        CurveType::Exponential => {
+            calculate_price_exponential(stage.start_price, stage_supply, stage.growth_rate)
+        },
+        CurveType::Logarithmic => {
+            calculate_price_logarithmic(stage.start_price, stage_supply, stage.growth_rate)
+        },
+        CurveType::ConstantProduct | CurveType::Piecewise => {
+            err!(TokenCreatorError::InvalidCurveStages)
+        }
+    }
+}
+
+fn stage_integral(stage: &CurveStage, stage_supply: u64) -> Option<u128> {
+    match stage.curve_type {
+        CurveType::Linear => {
+            // start_price * s + price_increment * s^2 / 2000
+            let supply = stage_supply as u128;
+            let slope_cost = (stage.price_increment as u128)
+                .checked_mul(supply)?
+                .checked_mul(supply)?
+                / 2000;
+            (stage.start_price as u128)
+                .checked_mul(supply)?
+                .checked_add(slope_cost)
+        },
+        CurveType::Exponential => {
+            exponential_integral(stage.start_price, stage_supply, stage.growth_rate)
+        },
+        CurveType::Logarithmic => {
+            logarithmic_integral(stage.start_price, stage_supply, stage.growth_rate)
+        },
+        CurveType::ConstantProduct | CurveType::Piecewise => None,
+    }
+}
+
+/// Advance the curve state for a buy of `tokens` paid with `sol_after_fee` lamports
+pub fn apply_buy_to_curve(
+    bonding_curve: &mut BondingCurve,
+    sol_after_fee: u64,
+    tokens: u64,
+) -> Result<()> {
+    bonding_curve.total_supply = bonding_curve.total_supply
+        .checked_add(tokens)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+
+    // Update price based on curve type
+    match bonding_curve.curve_type {
+        CurveType::Linear => {
+            let price_increase = bonding_curve.price_increment
+                .checked_mul(tokens)
+                .ok_or(TokenCreatorError::InvalidAmount)?
+                .checked_div(1000)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+            bonding_curve.current_price = bonding_curve.current_price
+                .checked_add(price_increase)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+        },
+        CurveType::Exponential => {
+            bonding_curve.current_price = calculate_price_exponential(
+                bonding_curve.initial_price,
+                bonding_curve.total_supply,
+                bonding_curve.growth_rate,
+            )?;
+        },
+        CurveType::Logarithmic => {
+            bonding_curve.current_price = calculate_price_logarithmic(
+                bonding_curve.initial_price,
+                bonding_curve.total_supply,
+                bonding_curve.growth_rate,
+            )?;
+        },
+        CurveType::ConstantProduct => {
+            bonding_curve.virtual_sol_reserves = bonding_curve.virtual_sol_reserves
+                .checked_add(sol_after_fee)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+            bonding_curve.virtual_token_reserves = bonding_curve.virtual_token_reserves
+                .checked_sub(tokens)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+            bonding_curve.current_price = bonding_curve.virtual_sol_reserves
+                / bonding_curve.virtual_token_reserves;
+        },
+        CurveType::Piecewise => {
+            bonding_curve.current_price =
+                calculate_price_piecewise(bonding_curve, bonding_curve.total_supply)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewind the curve state for a sell of `tokens` that released `sol_released` lamports
+pub fn apply_sell_to_curve(
+    bonding_curve: &mut BondingCurve,
+    sol_released: u64,
+    tokens: u64,
+) -> Result<()> {
+    bonding_curve.total_supply = bonding_curve.total_supply
+        .checked_sub(tokens)
+        .ok_or(TokenCreatorError::InsufficientBalance)?;
+
+    // Update price based on curve type
+    match bonding_curve.curve_type {
+        CurveType::Linear => {
+            let price_decrease = bonding_curve.price_increment
+                .checked_mul(tokens)
+                .ok_or(TokenCreatorError::InvalidAmount)?
+                .checked_div(1000)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+            bonding_curve.current_price = bonding_curve.current_price
+                .saturating_sub(price_decrease);
+        },
+        CurveType::Exponential => {
+            bonding_curve.current_price = calculate_price_exponential(
+                bonding_curve.initial_price,
+                bonding_curve.total_supply,
+                bonding_curve.growth_rate,
+            )?;
+        },
+        CurveType::Logarithmic => {
+            bonding_curve.current_price = calculate_price_logarithmic(
+                bonding_curve.initial_price,
+                bonding_curve.total_supply,
+                bonding_curve.growth_rate,
+            )?;
+        },
+        CurveType::ConstantProduct => {
+            bonding_curve.virtual_sol_reserves = bonding_curve.virtual_sol_reserves
+                .checked_sub(sol_released)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+            bonding_curve.virtual_token_reserves = bonding_curve.virtual_token_reserves
+                .checked_add(tokens)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+            bonding_curve.current_price = bonding_curve.virtual_sol_reserves
+                / bonding_curve.virtual_token_reserves;
+        },
+        CurveType::Piecewise => {
+            bonding_curve.current_price =
+                calculate_price_piecewise(bonding_curve, bonding_curve.total_supply)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Split the treasury at graduation into (liquidity, platform, creator)
+/// lamports. The creator takes the remainder so the shares add up exactly.
+pub fn calculate_graduation_split(bonding_curve: &BondingCurve) -> Result<(u64, u64, u64)> {
+    require!(
+        bonding_curve.graduation_liquidity_bps as u64
+            + bonding_curve.graduation_platform_bps as u64
+            + bonding_curve.graduation_creator_bps as u64 == 10000,
+        TokenCreatorError::InvalidAmount
+    );
+
+    let treasury_balance = bonding_curve.treasury_balance as u128;
+    let liquidity = treasury_balance * bonding_curve.graduation_liquidity_bps as u128 / 10000;
+    let platform = treasury_balance * bonding_curve.graduation_platform_bps as u128 / 10000;
+    let creator = treasury_balance - liquidity - platform;
+
+    Ok((liquidity as u64, platform as u64, creator as u64))
+}
+
+/// Whether a curve missed its graduation deadline and only allows refunds
+pub fn is_refund_active(bonding_curve: &BondingCurve, now: i64) -> bool {
+    !bonding_curve.is_graduated
+        && bonding_curve.graduation_deadline.is_some_and(|deadline| now > deadline)
+}
+
+/// Whether buys are still limited to the presale allowlist
+pub fn is_presale_active(bonding_curve: &BondingCurve, now: i64) -> bool {
+    bonding_curve.presale_merkle_root.is_some() && now < bonding_curve.presale_end
+}
+
+/// Whether buys are waiting on the opening auction to be settled
+pub fn is_opening_auction_pending(bonding_curve: &BondingCurve) -> bool {
+    bonding_curve.opening_auction_end != 0 && !bonding_curve.auction_settled
+}
+
+/// Fill of an opening auction with `committed_sol` lamports, bought as one
+/// trade at the curve's current state: (filled_sol, transaction_fee, curve
+/// tokens). When the commits would pass max_supply only the remaining
+/// supply is filled, and the rest of the SOL is refunded.
+pub fn calculate_auction_fill(
+    committed_sol: u64,
+    bonding_curve: &BondingCurve,
+) -> Result<(u64, u64, u64)> {
+    let transaction_fee = calculate_transaction_fee(committed_sol, bonding_curve)?;
+    let tokens =
+        calculate_tokens_for_sol_with_curve(committed_sol - transaction_fee, bonding_curve)?;
+    let remaining_supply = bonding_curve.max_supply.saturating_sub(bonding_curve.total_supply);
+    let decimals = bonding_curve.decimals;
+    if tokens <= remaining_supply {
+        // Only whole base units of the mint can be delivered; commits too
+        // small to buy one are refunded in full
+        let tokens = to_curve_units(to_raw_units(tokens, decimals)?, decimals)?;
+        if tokens == 0 {
+            return Ok((0, 0, 0));
+        }
+        return Ok((committed_sol, transaction_fee, tokens));
+    }
+
+    let tokens = to_curve_units(to_raw_units(remaining_supply, decimals)?, decimals)?;
+    let curve_cost = calculate_sol_for_exact_tokens(tokens, bonding_curve)?;
+    let filled_sol = gross_amount_for_net(curve_cost, bonding_curve.transaction_fee_bps)?
+        .min(committed_sol);
+    let transaction_fee = calculate_transaction_fee(filled_sol, bonding_curve)?;
+    Ok((filled_sol, transaction_fee, tokens))
+}
+
+/// A `commit_sol` commit's share of a settled opening auction: (curve
+/// tokens, lamports refunded), both pro rata and rounded down
+pub fn calculate_auction_share(
+    commit_sol: u64,
+    bonding_curve: &BondingCurve,
+) -> Result<(u64, u64)> {
+    let committed_sol = bonding_curve.auction_committed_sol as u128;
+    require!(
+        committed_sol > 0 && commit_sol as u128 <= committed_sol,
+        TokenCreatorError::InvalidAmount
+    );
+
+    let unfilled_sol = bonding_curve.auction_committed_sol
+        .checked_sub(bonding_curve.auction_filled_sol)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    let tokens = bonding_curve.auction_token_amount as u128 * commit_sol as u128 / committed_sol;
+    let refund_amount = unfilled_sol as u128 * commit_sol as u128 / committed_sol;
+    Ok((tokens as u64, refund_amount as u64))
+}
+
+/// Presale allowlist leaf for `wallet` with `allocation` lamports
+pub fn presale_leaf(wallet: &Pubkey, allocation: u64) -> [u8; 32] {
+    solana_program::hash::hashv(&[wallet.as_ref(), &allocation.to_le_bytes()]).to_bytes()
+}
+
+/// Whether `proof` links the (wallet, allocation) leaf to `merkle_root`.
+/// Each pair is hashed in sorted order, so proofs need no left/right flags.
+pub fn verify_presale_proof(
+    merkle_root: &[u8; 32],
+    wallet: &Pubkey,
+    allocation: u64,
+    proof: &[[u8; 32]],
+) -> bool {
+    let root = proof.iter().fold(presale_leaf(wallet, allocation), |node, sibling| {
+        let (first, second) = if node <= *sibling { (node, *sibling) } else { (*sibling, node) };
+        solana_program::hash::hashv(&[&first, &second]).to_bytes()
+    });
+    root == *merkle_root
+}
+
+/// Lamports refunded for burning `token_amount` curve units: the same share
+/// of the curve's SOL as of the outstanding supply, so the order in which
+/// holders claim doesn't matter
+pub fn calculate_refund_amount(token_amount: u64, bonding_curve: &BondingCurve) -> Result<u64> {
+    require!(
+        token_amount <= bonding_curve.total_supply,
+        TokenCreatorError::InsufficientBalance
+    );
+
+    let refund = (bonding_curve.curve_sol_balance as u128)
+        .checked_mul(token_amount as u128)
+        .ok_or(TokenCreatorError::InvalidAmount)?
+        .checked_div(bonding_curve.total_supply as u128)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    Ok(refund as u64)
 }
 
-#[derive(Accounts)]
-pub struct BurnTokens<'info> {
-    #[account(mut)]
-    pub mint: Account<'info, Mint>,
-    
-    #[account(mut)]
-    pub from: Account<'info, TokenAccount>,
-    
-    pub authority: Signer<'info>,
-    pub token_program: Program<'info, Token>,
-}
+/// Curve units to pair with `sol_amount` so the pool opens at the curve's
+/// closing price, capped at the supply the curve never minted
+pub fn calculate_pool_token_amount(sol_amount: u64, bonding_curve: &BondingCurve) -> Result<u64> {
+    let unminted = bonding_curve.max_supply.saturating_sub(bonding_curve.total_supply);
 
-#[derive(Accounts)]
-pub struct TransferTokens<'info> {
-    #[account(mut)]
-    pub from: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub to: Account<'info, TokenAccount>,
-    
-    pub authority: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    let tokens = match bonding_curve.curve_type {
+        CurveType::ConstantProduct => (sol_amount as u128)
+            .checked_mul(bonding_curve.virtual_token_reserves as u128)
+            .ok_or(TokenCreatorError::InvalidAmount)?
+            .checked_div(bonding_curve.virtual_sol_reserves as u128)
+            .ok_or(TokenCreatorError::InvalidAmount)?,
+        _ => (sol_amount as u128)
+            .checked_div(bonding_curve.current_price as u128)
+            .ok_or(TokenCreatorError::InvalidAmount)?,
+    };
+
+    Ok(std::cmp::min(tokens, unminted as u128) as u64)
 }
 
-#[derive(Accounts)]
-pub struct FreezeTokenAccount<'info> {
-    #[account(mut)]
-    pub account: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub mint: Account<'info, Mint>,
-    
-    pub authority: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+/// LP tokens minted for the initial deposit: sqrt(sol * tokens)
+pub fn calculate_initial_lp_amount(sol_amount: u64, token_amount: u64) -> Result<u64> {
+    let product = sol_amount as u128 * token_amount as u128;
+
+    // Integer square root by Newton's method
+    let mut root = product;
+    let mut next = product.div_ceil(2);
+    while next < root {
+        root = next;
+        next = (root + product / root) / 2;
+    }
+
+    u64::try_from(root).map_err(|_| error!(TokenCreatorError::InvalidAmount))
 }
 
-#[derive(Accounts)]
-pub struct ThawTokenAccount<'info> {
-    #[account(mut)]
-    pub account: Account<'info, TokenAccount>,
-    
-    #[account(mut)]
-    pub mint: Account<'info, Mint>,
-    
-    pub authority: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+/// Output of a constant-product pool swap after the POOL_SWAP_FEE_BPS fee,
+/// which stays in the pool
+pub fn calculate_pool_swap_output(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<u64> {
+    let amount_in_after_fee = (amount_in as u128)
+        .checked_mul((10000 - POOL_SWAP_FEE_BPS) as u128)
+        .ok_or(TokenCreatorError::InvalidAmount)?
+        / 10000;
+    let amount_out = amount_in_after_fee
+        .checked_mul(reserve_out as u128)
+        .ok_or(TokenCreatorError::InvalidAmount)?
+        .checked_div(reserve_in as u128 + amount_in_after_fee)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    Ok(amount_out as u64)
 }
 
-#[derive(Accounts)]
-pub struct InitializeBondingCurve<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + BondingCurve::INIT_SPACE,
-        seeds = [b"bonding_curve", mint.key().as_ref()],
-        bump
-    )]
-    pub bonding_curve: Account<'info, BondingCurve>,
-    
-    pub mint: Account<'info, Mint>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    #[account(
-        init_if_needed,
-        payer = authority,
-        space = 8 + Treasury::INIT_SPACE,
-        seeds = [b"treasury", mint.key().as_ref()],
-        bump
-    )]
-    pub treasury: Account<'info, Treasury>,
-    
-    pub system_program: Program<'info, System>,
+/// Whether Raydium CP-Swap puts the token before wrapped SOL in a pool
+fn cpmm_token_first(mint: &Pubkey) -> bool {
+    *mint < anchor_spl::token::spl_token::native_mint::ID
 }
 
-#[derive(Accounts)]
-pub struct BuyTokens<'info> {
-    #[account(
-        mut,
-        seeds = [b"bonding_curve", mint.key().as_ref()],
-        bump = bonding_curve.bump
-    )]
-    pub bonding_curve: Account<'info, BondingCurve>,
-    
-    #[account(mut)]
-    pub mint: Account<'info, Mint>,
-    
-    #[account(mut)]
-    pub buyer: Signer<'info>,
-    
-    #[account(mut)]
-    pub buyer_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        init_if_needed,
-        payer = buyer,
-        space = 8 + UserTracker::INIT_SPACE,
-        seeds = [b"user_tracker", mint.key().as_ref(), buyer.key().as_ref()],
-        bump
-    )]
-    pub user_tracker: Account<'info, UserTracker>,
-    
-    #[account(
-        mut,
-        seeds = [b"treasury", mint.key().as_ref()],
-        bump = treasury.bump
-    )]
-    pub treasury: Account<'info, Treasury>,
-    
-    /// Creator account - must be the authority that initialized the curve
-    #[account(
-        mut,
-        constraint = creator.key() == bonding_curve.authority @ TokenCreatorError::InsufficientAuthority
-    )]
-    pub creator: UncheckedAccount<'info>,
-    
-    /// CHECK: Platform treasury for receiving platform fees
-    #[account(mut)]
-    pub platform_treasury: UncheckedAccount<'info>,
-    
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+/// Build the pool-creation instruction of an external migration target,
+/// depositing `token_amount` raw tokens against `sol_amount` wrapped
+/// lamports. `accounts` must follow MigrationTarget::dex_account_layout.
+pub fn build_migration_instruction(
+    target: MigrationTarget,
+    mint: &Pubkey,
+    accounts: Vec<AccountMeta>,
+    token_amount: u64,
+    sol_amount: u64,
+    open_time: u64,
+) -> Result<Instruction> {
+    let program_id = target.program_id().ok_or(TokenCreatorError::WrongMigrationTarget)?;
+
+    let data = match target {
+        MigrationTarget::InHousePool => return err!(TokenCreatorError::WrongMigrationTarget),
+        MigrationTarget::RaydiumAmmV4 => {
+            // initialize2 { nonce, open_time, init_pc_amount, init_coin_amount }
+            let (_, nonce) = Pubkey::find_program_address(&[b"amm authority"], &program_id);
+            let mut data = vec![1u8, nonce];
+            data.extend_from_slice(&open_time.to_le_bytes());
+            data.extend_from_slice(&sol_amount.to_le_bytes());
+            data.extend_from_slice(&token_amount.to_le_bytes());
+            data
+        }
+        MigrationTarget::RaydiumCpmm => {
+            // initialize { init_amount_0, init_amount_1, open_time }
+            let (amount_0, amount_1) = if cpmm_token_first(mint) {
+                (token_amount, sol_amount)
+            } else {
+                (sol_amount, token_amount)
+            };
+            let mut data = solana_program::hash::hash(b"global:initialize").to_bytes()[..8].to_vec();
+            data.extend_from_slice(&amount_0.to_le_bytes());
+            data.extend_from_slice(&amount_1.to_le_bytes());
+            data.extend_from_slice(&open_time.to_le_bytes());
+            data
+        }
+    };
+
+    Ok(Instruction { program_id, accounts, data })
 }
 
-#[derive(Accounts)]
-pub struct SellTokens<'info> {
-    #[account(
-        mut,
-        seeds = [b"bonding_curve", mint.key().as_ref()],
-        bump = bonding_curve.bump
-    )]
-    pub bonding_curve: Account<'info, BondingCurve>,
-    
-    #[account(mut)]
-    pub mint: Account<'info, Mint>,
-    
-    #[account(mut)]
-    pub seller: Signer<'info>,
-    
-    #[account(mut)]
-    pub seller_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        seeds = [b"treasury", mint.key().as_ref()],
-        bump = treasury.bump
-    )]
-    pub treasury: Account<'info, Treasury>,
-    
-    /// Creator account - must be the authority that initialized the curve
-    #[account(
-        mut,
-        constraint = creator.key() == bonding_curve.authority @ TokenCreatorError::InsufficientAuthority
-    )]
-    pub creator: UncheckedAccount<'info>,
-    
-    /// CHECK: Platform treasury for receiving platform fees
-    #[account(mut)]
-    pub platform_treasury: UncheckedAccount<'info>,
-    
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+/// Convert a raw token amount of a mint with `decimals` into curve units, rounding down
+pub fn to_curve_units(raw_amount: u64, decimals: u8) -> Result<u64> {
+    if decimals <= CURVE_DECIMALS {
+        let factor = 10u64.pow((CURVE_DECIMALS - decimals) as u32);
+        Ok(raw_amount.checked_mul(factor).ok_or(TokenCreatorError::InvalidAmount)?)
+    } else {
+        Ok(raw_amount / 10u64.pow((decimals - CURVE_DECIMALS) as u32))
+    }
 }
 
-#[derive(Accounts)]
-pub struct GraduateToRaydium<'info> {
-    #[account(
-        mut,
-        seeds = [b"bonding_curve", mint.key().as_ref()],
-        bump = bonding_curve.bump
-    )]
-    pub bonding_curve: Account<'info, BondingCurve>,
-    
-    pub mint: Account<'info, Mint>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+/// Convert curve units into a raw token amount of a mint with `decimals`, rounding down
+pub fn to_raw_units(curve_amount: u64, decimals: u8) -> Result<u64> {
+    if decimals <= CURVE_DECIMALS {
+        Ok(curve_amount / 10u64.pow((CURVE_DECIMALS - decimals) as u32))
+    } else {
+        let factor = 10u64.pow((decimals - CURVE_DECIMALS) as u32);
+        Ok(curve_amount.checked_mul(factor).ok_or(TokenCreatorError::InvalidAmount)?)
+    }
 }
 
-#[derive(Accounts)]
-pub struct CreateRaydiumPool<'info> {
-    #[account(
-        seeds = [b"bonding_curve", mint.key().as_ref()],
-        bump = bonding_curve.bump
-    )]
-    pub bonding_curve: Account<'info, BondingCurve>,
-    
-    pub mint: Account<'info, Mint>,
-    
-    /// CHECK: Pool state account to be initialized
-    #[account(mut)]
-    pub pool_state: UncheckedAccount<'info>,
-    
-    #[account(
-        mut,
-        seeds = [b"treasury", mint.key().as_ref()],
-        bump = treasury.bump
-    )]
-    pub treasury: Account<'info, Treasury>,
-    
-    /// CHECK: Token vault for the pool
-    #[account(mut)]
-    pub token_vault: UncheckedAccount<'info>,
-    
-    /// CHECK: SOL vault for the pool
-    #[account(mut)]
-    pub sol_vault: UncheckedAccount<'info>,
-    
-    /// CHECK: LP mint account
-    #[account(mut)]
-    pub lp_mint: UncheckedAccount<'info>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    
-    /// CHECK: Raydium AMM program
-    pub raydium_program: UncheckedAccount<'info>,
+/// Split a transaction fee into its creator and platform shares. The platform
+/// takes the remainder so the two always add up to the full fee.
+pub fn split_transaction_fee(
+    transaction_fee: u64,
+    bonding_curve: &BondingCurve,
+) -> Result<(u64, u64)> {
+    if transaction_fee == 0 {
+        return Ok((0, 0));
+    }
+
+    let creator_fee = transaction_fee
+        .checked_mul(bonding_curve.creator_fee_bps as u64)
+        .ok_or(TokenCreatorError::InvalidAmount)?
+        .checked_div(bonding_curve.transaction_fee_bps as u64)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    let platform_fee = transaction_fee
+        .checked_sub(creator_fee)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+
+    Ok((creator_fee, platform_fee))
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct BondingCurve {
-    pub mint: Pubkey,
-    pub current_price: u64,
-    pub price_increment: u64,
-    pub total_supply: u64,
-    pub max_supply: u64,
-    pub authority: Pubkey,
-    pub bump: u8,
-    // New fields for enhanced bonding curve
-    pub initial_price: u64,
-    pub curve_type: CurveType,
-    pub growth_rate: u64, // Basis points (10000 = 100%)
-    pub treasury_balance: u64,
-    pub total_volume: u64,
-    pub graduation_threshold: u64, // Fixed at 500 SOL for all tokens
-    pub is_graduated: bool,
-    pub creation_fee: u64,
-    pub transaction_fee_bps: u16, // Basis points (100 = 1%)
-    pub creator_fee_bps: u16,
-    pub platform_fee_bps: u16,
-    // Anti-bot protection fields
-    pub creation_timestamp: i64,
-    pub launch_protection_period: i64, // Duration in seconds (e.g., 3600 for 1 hour)
-    pub max_buy_during_protection: u64, // Max SOL per buy during protection period
-    pub transaction_cooldown: u64, // Minimum seconds between transactions per wallet
-    pub max_price_impact_bps: u16, // Maximum price impact in basis points (500 = 5%)
+/// Outcome of a buy paying `sol_amount` lamports at `now`, as buy_tokens would execute it
+pub fn calculate_buy_quote(
+    sol_amount: u64,
+    bonding_curve: &BondingCurve,
+    now: i64,
+) -> Result<TradeQuote> {
+    let transaction_fee = calculate_transaction_fee(sol_amount, bonding_curve)?;
+    let sniper_tax = calculate_sniper_tax(sol_amount, bonding_curve, now)?;
+    let sol_after_fee = sol_amount
+        .checked_sub(transaction_fee)
+        .and_then(|amount| amount.checked_sub(sniper_tax))
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    let tokens_to_mint = calculate_tokens_for_sol_with_curve(sol_after_fee, bonding_curve)?;
+    let raw_tokens = to_raw_units(tokens_to_mint, bonding_curve.decimals)?;
+    require!(raw_tokens > 0, TokenCreatorError::InvalidAmount);
+    let tokens_to_mint = to_curve_units(raw_tokens, bonding_curve.decimals)?;
+    require!(
+        bonding_curve.total_supply + tokens_to_mint <= bonding_curve.max_supply,
+        TokenCreatorError::ExceedsMaxSupply
+    );
+
+    let (creator_fee, platform_fee) = split_transaction_fee(transaction_fee, bonding_curve)?;
+    let price_impact_bps = calculate_price_impact(sol_after_fee, bonding_curve)?;
+
+    let mut simulated = bonding_curve.clone();
+    apply_buy_to_curve(&mut simulated, sol_after_fee, tokens_to_mint)?;
+
+    Ok(TradeQuote {
+        token_amount: raw_tokens,
+        sol_amount,
+        transaction_fee,
+        creator_fee,
+        platform_fee,
+        new_price: simulated.current_price,
+        price_impact_bps,
+        sniper_tax,
+    })
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct PoolState {
-    pub is_initialized: bool,
-    pub mint: Pubkey,
-    pub lp_mint: Pubkey,
-    pub token_vault: Pubkey,
-    pub sol_vault: Pubkey,
-    pub creation_timestamp: i64,
+/// Outcome of selling `token_amount` raw tokens, as sell_tokens would execute it
+pub fn calculate_sell_quote(token_amount: u64, bonding_curve: &BondingCurve) -> Result<TradeQuote> {
+    let token_amount = to_curve_units(token_amount, bonding_curve.decimals)?;
+    require!(token_amount > 0, TokenCreatorError::InvalidAmount);
+    let sol_to_return_gross = calculate_sol_for_tokens_with_curve(token_amount, bonding_curve)?;
+    let transaction_fee = calculate_transaction_fee(sol_to_return_gross, bonding_curve)?;
+    let sol_to_return_net = sol_to_return_gross
+        .checked_sub(transaction_fee)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    let (creator_fee, platform_fee) = split_transaction_fee(transaction_fee, bonding_curve)?;
+
+    let mut simulated = bonding_curve.clone();
+    apply_sell_to_curve(&mut simulated, sol_to_return_gross, token_amount)?;
+
+    let price_impact_bps = calculate_sell_price_impact(token_amount, bonding_curve)?;
+
+    Ok(TradeQuote {
+        token_amount: to_raw_units(token_amount, bonding_curve.decimals)?,
+        sol_amount: sol_to_return_net,
+        transaction_fee,
+        creator_fee,
+        platform_fee,
+        new_price: simulated.current_price,
+        price_impact_bps,
+        sniper_tax: 0,
+    })
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct UserTracker {
-    pub wallet: Pubkey,
-    pub mint: Pubkey,
-    pub last_transaction_time: i64,
-    pub total_bought_sol: u64,
-    pub transaction_count: u32,
-    pub bump: u8,
+/// Referrer's cut of a transaction fee, rounded down
+pub fn calculate_referral_fee(transaction_fee: u64, bonding_curve: &BondingCurve) -> Result<u64> {
+    if transaction_fee == 0 || bonding_curve.referral_fee_bps == 0 {
+        return Ok(0);
+    }
+
+    transaction_fee
+        .checked_mul(bonding_curve.referral_fee_bps as u64)
+        .ok_or(TokenCreatorError::InvalidAmount)?
+        .checked_div(bonding_curve.transaction_fee_bps as u64)
+        .ok_or_else(|| error!(TokenCreatorError::InvalidAmount))
 }
 
-#[account]
-#[derive(InitSpace)]
-pub struct Treasury {
-    pub authority: Pubkey,
-    pub total_collected: u64,
-    pub bump: u8,
+/// Sniper tax rate on buys at `now`, in basis points on top of the
+/// transaction fee. The total rate starts at sniper_tax_start_bps when the
+/// curve is created and falls linearly to the base fee by the end of
+/// launch_protection_period; a clock behind creation counts as launch.
+pub fn calculate_sniper_tax_bps(bonding_curve: &BondingCurve, now: i64) -> u16 {
+    let period = bonding_curve.launch_protection_period;
+    let age = now.saturating_sub(bonding_curve.creation_timestamp).max(0);
+    if age >= period || bonding_curve.sniper_tax_start_bps <= bonding_curve.transaction_fee_bps {
+        return 0;
+    }
+
+    let surcharge = bonding_curve.sniper_tax_start_bps - bonding_curve.transaction_fee_bps;
+    (surcharge as i128 * (period - age) as i128 / period as i128) as u16
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, InitSpace)]
-pub enum CurveType {
-    Linear,
-    Exponential,
-    Logarithmic,
+/// Most raw tokens one wallet may hold during the protection period, or None
+/// when the curve has no holding cap
+pub fn calculate_max_wallet_holding(bonding_curve: &BondingCurve) -> Result<Option<u64>> {
+    if bonding_curve.max_wallet_holding_bps == 0 {
+        return Ok(None);
+    }
+
+    let max_holding = bonding_curve.max_supply as u128
+        * bonding_curve.max_wallet_holding_bps as u128
+        / 10000;
+    to_raw_units(max_holding as u64, bonding_curve.decimals).map(Some)
 }
 
-// Enhanced bonding curve calculation functions
-pub fn calculate_price_exponential(
-    initial_price: u64,
-    total_supply: u64,
-    growth_rate: u64, // in basis points
+/// Sniper tax charged on a buy of `sol_amount` gross lamports at `now`
+pub fn calculate_sniper_tax(
+    sol_amount: u64,
+    bonding_curve: &BondingCurve,
+    now: i64,
 ) -> Result<u64> {
-    // Exponential curve: price = initial_price * (1 + growth_rate/10000) ^ (supply / scale)
-    // Using fixed-point arithmetic to avoid floating point
-    
-    if total_supply == 0 {
-        return Ok(initial_price);
+    sol_amount
+        .checked_mul(calculate_sniper_tax_bps(bonding_curve, now) as u64)
+        .ok_or(TokenCreatorError::InvalidAmount)?
+        .checked_div(10000)
+        .ok_or_else(|| error!(TokenCreatorError::InvalidAmount))
+}
+
+/// Transaction fee charged on a gross lamport amount
+pub fn calculate_transaction_fee(sol_amount: u64, bonding_curve: &BondingCurve) -> Result<u64> {
+    sol_amount
+        .checked_mul(bonding_curve.transaction_fee_bps as u64)
+        .ok_or(TokenCreatorError::InvalidAmount)?
+        .checked_div(10000)
+        .ok_or_else(|| error!(TokenCreatorError::InvalidAmount))
+}
+
+/// Smallest gross amount that still leaves `net_amount` after a `fee_bps` fee
+pub fn gross_amount_for_net(net_amount: u64, fee_bps: u16) -> Result<u64> {
+    require!(fee_bps < 10000, TokenCreatorError::InvalidAmount);
+
+    let gross = (net_amount as u128 * 10000).div_ceil(10000 - fee_bps as u128);
+    let mut gross = u64::try_from(gross).map_err(|_| error!(TokenCreatorError::InvalidAmount))?;
+
+    // The fee rounds down, so the ceiling can overshoot by a lamport
+    while gross > net_amount {
+        let candidate = gross - 1;
+        let fee = (candidate as u128 * fee_bps as u128 / 10000) as u64;
+        if candidate - fee < net_amount {
+            break;
+        }
+        gross = candidate;
     }
-    
-    // Scale factor to control growth rate
-    let scale_factor = 1000;
-    let supply_scaled = total_supply / scale_factor;
-    
-    // Calculate (1 + growth_rate/10000)^supply_scaled using fast exponentiation
-    let growth_multiplier = 10000u128 + growth_rate as u128; // e.g., 10100 for 1% growth
-    
-    // Use u128 for intermediate calculations to prevent overflow
-    let mut result = 10000u128; // Start with 1.0 in fixed point
-    let mut base = growth_multiplier;
-    let mut exp = supply_scaled;
-    
-    // Fast exponentiation by squaring - O(log n) instead of O(n)
-    while exp > 0 {
-        if exp & 1 == 1 {
-            // If exp is odd, multiply result by base
-            result = result
-                .checked_mul(base)
-                .ok_or(TokenCreatorError::InvalidAmount)?
-                .checked_div(10000)
-                .ok_or(TokenCreatorError::InvalidAmount)?;
+
+    Ok(gross)
+}
+
+/// Fewest tokens whose sale pays at least `sol_out` lamports after fees.
+/// Returns the token amount and the gross lamports the curve releases for it.
+pub fn calculate_tokens_for_exact_sol(
+    sol_out: u64,
+    bonding_curve: &BondingCurve,
+) -> Result<(u64, u64)> {
+    let net_for = |token_amount: u64| -> Result<(u64, u64)> {
+        let gross = calculate_sol_for_tokens_with_curve(token_amount, bonding_curve)?;
+        let fee = calculate_transaction_fee(gross, bonding_curve)?;
+        Ok((gross - fee, gross))
+    };
+
+    // Even selling the whole supply must cover the request
+    let (max_net, _) = net_for(bonding_curve.total_supply)?;
+    require!(max_net >= sol_out, TokenCreatorError::InsufficientBalance);
+
+    // Net proceeds grow with the amount sold, so binary search the smallest fit
+    let mut low = 1;
+    let mut high = bonding_curve.total_supply;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if net_for(mid)?.0 >= sol_out {
+            high = mid;
+        } else {
+            low = mid + 1;
         }
-        
-        // Square the base
-        base = base
-            .checked_mul(base)
-            .ok_or(TokenCreatorError::InvalidAmount)?
-            .checked_div(10000)
-            .ok_or(TokenCreatorError::InvalidAmount)?;
-        
-        exp >>= 1; // Divide exp by 2
     }
-    
-    // Apply result to initial price and convert back to u64
-    let price_u128 = (initial_price as u128)
-        .checked_mul(result)
-        .ok_or(TokenCreatorError::InvalidAmount)?
-        .checked_div(10000)
+
+    Ok((low, net_for(low)?.1))
+}
+
+/// Lamports the curve needs (before fees) to mint exactly `token_amount` tokens
+pub fn calculate_sol_for_exact_tokens(
+    token_amount: u64,
+    bonding_curve: &BondingCurve,
+) -> Result<u64> {
+    let new_supply = bonding_curve.total_supply
+        .checked_add(token_amount)
         .ok_or(TokenCreatorError::InvalidAmount)?;
-    
-    // Ensure result fits in u64
-    if price_u128 > u64::MAX as u128 {
-        return Err(TokenCreatorError::InvalidAmount);
+    require!(new_supply <= bonding_curve.max_supply, TokenCreatorError::ExceedsMaxSupply);
+
+    let integral_cost = |integral: &dyn Fn(u64) -> Option<u128>| -> Result<u64> {
+        let cost = integral(new_supply)
+            .zip(integral(bonding_curve.total_supply))
+            .and_then(|(after, before)| after.checked_sub(before))
+            .ok_or(TokenCreatorError::InvalidAmount)?;
+        u64::try_from(cost).map_err(|_| error!(TokenCreatorError::InvalidAmount))
+    };
+
+    match bonding_curve.curve_type {
+        CurveType::Linear => {
+            // Inverse of tokens = sol / current_price
+            token_amount
+                .checked_mul(bonding_curve.current_price)
+                .ok_or_else(|| error!(TokenCreatorError::InvalidAmount))
+        },
+        CurveType::Exponential => integral_cost(&|supply| {
+            exponential_integral(bonding_curve.initial_price, supply, bonding_curve.growth_rate)
+        }),
+        CurveType::Logarithmic => integral_cost(&|supply| {
+            logarithmic_integral(bonding_curve.initial_price, supply, bonding_curve.growth_rate)
+        }),
+        CurveType::ConstantProduct => {
+            // dx = k / (y - dy) - x, rounded up so the buy yields at least dy
+            let sol_reserves = bonding_curve.virtual_sol_reserves as u128;
+            let token_reserves = bonding_curve.virtual_token_reserves as u128;
+            require!(
+                (token_amount as u128) < token_reserves,
+                TokenCreatorError::ExceedsMaxSupply
+            );
+            let invariant = sol_reserves
+                .checked_mul(token_reserves)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+            let cost = invariant
+                .div_ceil(token_reserves - token_amount as u128)
+                .saturating_sub(sol_reserves);
+            u64::try_from(cost).map_err(|_| error!(TokenCreatorError::InvalidAmount))
+        },
+        CurveType::Piecewise => integral_cost(&|supply| piecewise_integral(bonding_curve, supply)),
     }
-    
-    Ok(price_u128 as u64)
 }
 
 pub fn calculate_tokens_for_sol_with_curve(
@@ -1166,18 +4586,33 @@ pub fn calculate_tokens_for_sol_with_curve(
                 .ok_or(TokenCreatorError::InvalidAmount)
         },
         CurveType::Exponential => {
-            // For exponential curve, we need to integrate to find exact tokens
-            // Simplified: estimate tokens based on average price
-            let avg_price = bonding_curve.current_price;
-            sol_amount
-                .checked_div(avg_price)
-                .ok_or(TokenCreatorError::InvalidAmount)
+            // Integrate the price over the supply range instead of filling the
+            // whole order at the current price
+            integral_tokens_for_sol(sol_amount, bonding_curve, |supply| {
+                exponential_integral(bonding_curve.initial_price, supply, bonding_curve.growth_rate)
+            })
         },
         CurveType::Logarithmic => {
-            // Logarithmic implementation (future)
-            sol_amount
-                .checked_div(bonding_curve.current_price)
-                .ok_or(TokenCreatorError::InvalidAmount)
+            integral_tokens_for_sol(sol_amount, bonding_curve, |supply| {
+                logarithmic_integral(bonding_curve.initial_price, supply, bonding_curve.growth_rate)
+            })
+        },
+        CurveType::ConstantProduct => {
+            // tokens = y - k / (x + dx), rounding the new token reserve up
+            let sol_reserves = bonding_curve.virtual_sol_reserves as u128;
+            let token_reserves = bonding_curve.virtual_token_reserves as u128;
+            let invariant = sol_reserves
+                .checked_mul(token_reserves)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+            let new_token_reserves = invariant
+                .div_ceil(sol_reserves + sol_amount as u128);
+
+            Ok((token_reserves - new_token_reserves) as u64)
+        },
+        CurveType::Piecewise => {
+            integral_tokens_for_sol(sol_amount, bonding_curve, |supply| {
+                piecewise_integral(bonding_curve, supply)
+            })
         }
     }
 }
@@ -1194,31 +4629,100 @@ pub fn calculate_sol_for_tokens_with_curve(
                 .ok_or(TokenCreatorError::InvalidAmount)
         },
         CurveType::Exponential => {
-            // For selling, calculate price after tokens are removed
-            let new_supply = bonding_curve.total_supply.saturating_sub(token_amount);
-            let new_price = calculate_price_exponential(
-                bonding_curve.initial_price,
-                new_supply,
-                bonding_curve.growth_rate
-            )?;
-            
-            // Average price for the transaction
-            let avg_price = bonding_curve.current_price
-                .checked_add(new_price)
+            // Same integral as the buy side, so a round trip nets out exactly
+            integral_sol_for_tokens(token_amount, bonding_curve, |supply| {
+                exponential_integral(bonding_curve.initial_price, supply, bonding_curve.growth_rate)
+            })
+        },
+        CurveType::Logarithmic => {
+            integral_sol_for_tokens(token_amount, bonding_curve, |supply| {
+                logarithmic_integral(bonding_curve.initial_price, supply, bonding_curve.growth_rate)
+            })
+        },
+        CurveType::ConstantProduct => {
+            // sol = x - k / (y + dy), rounding the new SOL reserve up
+            require!(
+                token_amount <= bonding_curve.total_supply,
+                TokenCreatorError::InsufficientBalance
+            );
+            let sol_reserves = bonding_curve.virtual_sol_reserves as u128;
+            let token_reserves = bonding_curve.virtual_token_reserves as u128;
+            let invariant = sol_reserves
+                .checked_mul(token_reserves)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+            let new_sol_reserves = invariant
+                .div_ceil(token_reserves + token_amount as u128);
+
+            Ok((sol_reserves - new_sol_reserves) as u64)
+        },
+        CurveType::Piecewise => {
+            integral_sol_for_tokens(token_amount, bonding_curve, |supply| {
+                piecewise_integral(bonding_curve, supply)
+            })
+        }
+    }
+}
+
+/// Market cap in lamports: circulating supply valued at the current spot price
+pub fn calculate_market_cap(bonding_curve: &BondingCurve) -> Result<u64> {
+    match bonding_curve.curve_type {
+        CurveType::ConstantProduct => {
+            // Use the reserve ratio directly; current_price is rounded down
+            let market_cap = (bonding_curve.total_supply as u128)
+                .checked_mul(bonding_curve.virtual_sol_reserves as u128)
                 .ok_or(TokenCreatorError::InvalidAmount)?
-                .checked_div(2)
+                .checked_div(bonding_curve.virtual_token_reserves as u128)
                 .ok_or(TokenCreatorError::InvalidAmount)?;
-            token_amount
-                .checked_mul(avg_price)
-                .ok_or(TokenCreatorError::InvalidAmount)
+            u64::try_from(market_cap).map_err(|_| error!(TokenCreatorError::InvalidAmount))
         },
-        CurveType::Logarithmic => {
-            // Logarithmic implementation (future)
-            token_amount
-                .checked_mul(bonding_curve.current_price)
-                .ok_or(TokenCreatorError::InvalidAmount)
+        _ => bonding_curve.total_supply
+            .checked_mul(bonding_curve.current_price)
+            .ok_or_else(|| error!(TokenCreatorError::InvalidAmount)),
+    }
+}
+
+/// Largest token amount whose integral cost from the current supply fits in `sol_amount`.
+///
+/// The search is capped one past max_supply so oversized buys still surface
+/// as ExceedsMaxSupply in the caller.
+fn integral_tokens_for_sol(
+    sol_amount: u64,
+    bonding_curve: &BondingCurve,
+    integral: impl Fn(u64) -> Option<u128>,
+) -> Result<u64> {
+    let start_supply = bonding_curve.total_supply;
+    let budget = integral(start_supply)
+        .and_then(|cost| cost.checked_add(sol_amount as u128))
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+
+    let mut low = start_supply;
+    let mut high = bonding_curve.max_supply.saturating_add(1).max(start_supply);
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        match integral(mid) {
+            Some(cost) if cost <= budget => low = mid,
+            _ => high = mid - 1,
         }
     }
+
+    Ok(low - start_supply)
+}
+
+/// Lamports released by removing `token_amount` from the current supply
+fn integral_sol_for_tokens(
+    token_amount: u64,
+    bonding_curve: &BondingCurve,
+    integral: impl Fn(u64) -> Option<u128>,
+) -> Result<u64> {
+    let new_supply = bonding_curve.total_supply
+        .checked_sub(token_amount)
+        .ok_or(TokenCreatorError::InsufficientBalance)?;
+
+    let sol_amount = integral(bonding_curve.total_supply)
+        .zip(integral(new_supply))
+        .and_then(|(before, after)| before.checked_sub(after))
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    u64::try_from(sol_amount).map_err(|_| error!(TokenCreatorError::InvalidAmount))
 }
 
 /// Calculate price impact of a trade in basis points
@@ -1230,7 +4734,7 @@ pub fn calculate_price_impact(
     
     // Calculate tokens that would be bought
     let tokens_to_buy = calculate_tokens_for_sol_with_curve(sol_amount, bonding_curve)?;
-    
+
     // Calculate new price after the trade
     let new_supply = bonding_curve.total_supply + tokens_to_buy;
     let new_price = match bonding_curve.curve_type {
@@ -1252,15 +4756,16 @@ pub fn calculate_price_impact(
             )?
         },
         CurveType::Logarithmic => {
-            let price_increase = bonding_curve.price_increment
-                .checked_mul(tokens_to_buy)
-                .ok_or(TokenCreatorError::InvalidAmount)?
-                .checked_div(1000)
-                .ok_or(TokenCreatorError::InvalidAmount)?;
-            bonding_curve.current_price
-                .checked_add(price_increase)
-                .ok_or(TokenCreatorError::InvalidAmount)?
-        }
+            calculate_price_logarithmic(
+                bonding_curve.initial_price,
+                new_supply,
+                bonding_curve.growth_rate,
+            )?
+        },
+        CurveType::ConstantProduct => {
+            return constant_product_price_impact(sol_amount, tokens_to_buy, bonding_curve);
+        },
+        CurveType::Piecewise => calculate_price_piecewise(bonding_curve, new_supply)?,
     };
     
     // Calculate price impact as percentage in basis points
@@ -1279,6 +4784,116 @@ pub fn calculate_price_impact(
     Ok(std::cmp::min(impact_bps, u16::MAX as u64) as u16)
 }
 
+/// Price impact of a constant-product buy, compared exactly on the reserve
+/// ratios: new/old - 1 = (x' * y) / (x * y') - 1
+fn constant_product_price_impact(
+    sol_amount: u64,
+    tokens_to_buy: u64,
+    bonding_curve: &BondingCurve,
+) -> Result<u16> {
+    let sol_reserves = bonding_curve.virtual_sol_reserves as u128;
+    let token_reserves = bonding_curve.virtual_token_reserves as u128;
+    let new_sol_reserves = sol_reserves + sol_amount as u128;
+    let new_token_reserves = token_reserves - tokens_to_buy as u128;
+
+    let old_value = sol_reserves
+        .checked_mul(new_token_reserves)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    let impact_bps = new_sol_reserves
+        .checked_mul(token_reserves)
+        .ok_or(TokenCreatorError::InvalidAmount)?
+        .saturating_sub(old_value)
+        .checked_mul(10000)
+        .ok_or(TokenCreatorError::InvalidAmount)?
+        .checked_div(old_value)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+
+    Ok(std::cmp::min(impact_bps, u16::MAX as u128) as u16)
+}
+
+/// Calculate the price drop caused by selling `token_amount` tokens, in basis points
+pub fn calculate_sell_price_impact(
+    token_amount: u64,
+    bonding_curve: &BondingCurve,
+) -> Result<u16> {
+    let current_price = bonding_curve.current_price;
+    let new_supply = bonding_curve.total_supply
+        .checked_sub(token_amount)
+        .ok_or(TokenCreatorError::InsufficientBalance)?;
+
+    // Calculate new price after the trade
+    let new_price = match bonding_curve.curve_type {
+        CurveType::Linear => {
+            let price_decrease = bonding_curve.price_increment
+                .checked_mul(token_amount)
+                .ok_or(TokenCreatorError::InvalidAmount)?
+                .checked_div(1000)
+                .ok_or(TokenCreatorError::InvalidAmount)?;
+            current_price.saturating_sub(price_decrease)
+        },
+        CurveType::Exponential => {
+            calculate_price_exponential(
+                bonding_curve.initial_price,
+                new_supply,
+                bonding_curve.growth_rate,
+            )?
+        },
+        CurveType::Logarithmic => {
+            calculate_price_logarithmic(
+                bonding_curve.initial_price,
+                new_supply,
+                bonding_curve.growth_rate,
+            )?
+        },
+        CurveType::ConstantProduct => {
+            let sol_amount = calculate_sol_for_tokens_with_curve(token_amount, bonding_curve)?;
+            return constant_product_sell_price_impact(sol_amount, token_amount, bonding_curve);
+        },
+        CurveType::Piecewise => calculate_price_piecewise(bonding_curve, new_supply)?,
+    };
+
+    if new_price >= current_price || current_price == 0 {
+        return Ok(0);
+    }
+
+    let impact_bps = (current_price - new_price)
+        .checked_mul(10000)
+        .ok_or(TokenCreatorError::InvalidAmount)?
+        / current_price;
+
+    Ok(std::cmp::min(impact_bps, u16::MAX as u64) as u16)
+}
+
+/// Price drop of a constant-product sell, compared exactly on the reserve
+/// ratios: 1 - new/old = 1 - (x' * y) / (x * y')
+fn constant_product_sell_price_impact(
+    sol_amount: u64,
+    token_amount: u64,
+    bonding_curve: &BondingCurve,
+) -> Result<u16> {
+    let sol_reserves = bonding_curve.virtual_sol_reserves as u128;
+    let token_reserves = bonding_curve.virtual_token_reserves as u128;
+    let new_sol_reserves = sol_reserves
+        .checked_sub(sol_amount as u128)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    let new_token_reserves = token_reserves + token_amount as u128;
+
+    let old_value = sol_reserves
+        .checked_mul(new_token_reserves)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    let new_value = new_sol_reserves
+        .checked_mul(token_reserves)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+    let impact_bps = old_value
+        .saturating_sub(new_value)
+        .checked_mul(10000)
+        .ok_or(TokenCreatorError::InvalidAmount)?
+        .checked_div(old_value)
+        .ok_or(TokenCreatorError::InvalidAmount)?;
+
+    Ok(std::cmp::min(impact_bps, u16::MAX as u128) as u16)
+}
+
 #[error_code]
 pub enum TokenCreatorError {
     #[msg("Invalid token name")]
@@ -1315,4 +4930,48 @@ pub enum TokenCreatorError {
     ExceedsProtectionLimit,
     #[msg("Price impact exceeds maximum allowed")]
     ExceedsPriceImpactLimit,
+    #[msg("Invalid curve stage configuration")]
+    InvalidCurveStages,
+    #[msg("Trade output is below the minimum requested")]
+    SlippageExceeded,
+    #[msg("Transaction deadline has passed")]
+    TransactionExpired,
+    #[msg("Launch settings can only be changed before the first trade")]
+    LaunchConfigLocked,
+    #[msg("Instruction does not match the curve's migration target")]
+    WrongMigrationTarget,
+    #[msg("DEX accounts do not match the migration target's layout")]
+    InvalidMigrationAccounts,
+    #[msg("Curve missed its graduation deadline and only allows refunds")]
+    RefundModeActive,
+    #[msg("Refunds are only available once the graduation deadline has passed")]
+    RefundModeInactive,
+    #[msg("Config value outside the allowed range")]
+    InvalidConfig,
+    #[msg("Trading is paused by the protocol admin")]
+    TradingPaused,
+    #[msg("No fees to claim")]
+    NoFeesToClaim,
+    #[msg("Traders cannot refer themselves")]
+    SelfReferral,
+    #[msg("Purchase exceeds the wallet's protection period limit")]
+    ExceedsWalletBuyLimit,
+    #[msg("Purchase exceeds the wallet's holding limit during the protection period")]
+    ExceedsWalletHoldingLimit,
+    #[msg("Only allowlisted wallets can buy during the presale")]
+    NotOnPresaleAllowlist,
+    #[msg("Purchase exceeds the wallet's presale allocation")]
+    ExceedsPresaleAllocation,
+    #[msg("A curve cannot have both a presale and an opening auction")]
+    ConflictingLaunchModes,
+    #[msg("Trading opens once the opening auction is settled")]
+    OpeningAuctionPending,
+    #[msg("The opening auction is not taking commits")]
+    OpeningAuctionClosed,
+    #[msg("The opening auction has not ended yet")]
+    OpeningAuctionNotEnded,
+    #[msg("The curve has no opening auction left to settle")]
+    NoOpeningAuction,
+    #[msg("Liquidity share does not cover the DEX migration budget")]
+    LiquidityBelowMigrationBudget,
 }
\ No newline at end of file
@@ -0,0 +1,1027 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    hash::{hash, hashv},
+    instruction::AccountMeta,
+};
+use anchor_spl::token::spl_token::native_mint;
+use degenie_token_creator::{
+    apply_buy_to_curve, apply_sell_to_curve, build_curve_stages, build_migration_instruction,
+    calculate_auction_fill, calculate_auction_share, calculate_buy_quote,
+    calculate_exponential_integral, calculate_graduation_split, calculate_initial_lp_amount,
+    calculate_logarithmic_integral, calculate_market_cap, calculate_max_wallet_holding,
+    calculate_pool_swap_output, calculate_pool_token_amount, calculate_price_exponential,
+    calculate_price_impact, calculate_price_logarithmic, calculate_price_piecewise,
+    calculate_referral_fee, calculate_refund_amount, calculate_sell_price_impact,
+    calculate_sell_quote, calculate_sniper_tax, calculate_sniper_tax_bps,
+    calculate_sol_for_exact_tokens, calculate_sol_for_tokens_with_curve,
+    calculate_tokens_for_exact_sol, calculate_tokens_for_sol_with_curve, calculate_transaction_fee,
+    gross_amount_for_net, is_presale_active, is_refund_active, presale_leaf, split_transaction_fee,
+    to_curve_units, to_raw_units, validate_config_params, verify_presale_proof, BondingCurve,
+    ConfigParams, CurveStageParams, CurveType, MigrationTarget, SniperTaxRecipient,
+    DEX_MIGRATION_BUDGET, LAMPORTS_PER_SOL, MAX_SNIPER_TAX_BPS, MAX_TRANSACTION_FEE_BPS,
+    RAYDIUM_AMM_V4_PROGRAM_ID, RAYDIUM_CPMM_PROGRAM_ID,
+};
+
+// The fixture curves launch at 0 with a one-hour protection period
+const LAUNCH_WINDOW_OVER: i64 = 3600;
+const GRADUATION_THRESHOLD: u64 = 500 * LAMPORTS_PER_SOL;
+
+fn exponential_curve(total_supply: u64) -> BondingCurve {
+    let current_price = calculate_price_exponential(1000, total_supply, 100).unwrap();
+    curve(CurveType::Exponential, 100, total_supply, current_price) // 1% per 1000 tokens
+}
+
+fn logarithmic_curve(total_supply: u64) -> BondingCurve {
+    let current_price = calculate_price_logarithmic(1000, total_supply, 5000).unwrap();
+    curve(CurveType::Logarithmic, 5000, total_supply, current_price) // +50% per unit of ln
+}
+
+fn curve(curve_type: CurveType, growth_rate: u64, total_supply: u64, current_price: u64) -> BondingCurve {
+    let initial_price = 1000; // 0.001 SOL per token
+    BondingCurve {
+        mint: Pubkey::new_unique(),
+        current_price,
+        price_increment: 100,
+        total_supply,
+        max_supply: 1_000_000,
+        authority: Pubkey::new_unique(),
+        bump: 255,
+        initial_price,
+        curve_type,
+        growth_rate,
+        treasury_balance: 0,
+        total_volume: 0,
+        graduation_threshold: GRADUATION_THRESHOLD,
+        is_graduated: false,
+        creation_fee: 20_000_000,
+        transaction_fee_bps: 100,
+        creator_fee_bps: 50,
+        platform_fee_bps: 50,
+        creation_timestamp: 0,
+        launch_protection_period: 3600,
+        max_buy_during_protection: 1_000_000_000,
+        transaction_cooldown: 30,
+        max_price_impact_bps: 500,
+        virtual_sol_reserves: 0,
+        virtual_token_reserves: 0,
+        stages: vec![],
+        max_sell_price_impact_bps: 500,
+        decimals: 6,
+        graduation_liquidity_bps: 8500,
+        graduation_platform_bps: 1000,
+        graduation_creator_bps: 500,
+        migration_target: MigrationTarget::InHousePool,
+        is_migrated: false,
+        graduation_platform_payout: 0,
+        graduation_creator_payout: 0,
+        graduation_keeper_reward: 0,
+        graduation_deadline: None,
+        paused: false,
+        creator_fees_owed: 0,
+        platform_fees_owed: 0,
+        referral_fee_bps: 20,
+        sniper_tax_start_bps: 5000,
+        sniper_tax_recipient: SniperTaxRecipient::Treasury,
+        max_wallet_buy_during_protection: 5 * LAMPORTS_PER_SOL,
+        max_wallet_holding_bps: 200,
+        presale_merkle_root: None,
+        presale_end: 0,
+        opening_auction_end: 0,
+        auction_committed_sol: 0,
+        auction_filled_sol: 0,
+        auction_token_amount: 0,
+        auction_settled: false,
+        curve_sol_balance: 0,
+    }
+}
+
+fn stage_params() -> Vec<CurveStageParams> {
+    vec![
+        // Stealth: gentle linear ramp
+        CurveStageParams { curve_type: CurveType::Linear, end_supply: 100_000, growth_rate: 0, price_increment: 10 },
+        // Growth: 1% per 1000 tokens
+        CurveStageParams { curve_type: CurveType::Exponential, end_supply: 400_000, growth_rate: 100, price_increment: 0 },
+        // Maturity: flattening log curve
+        CurveStageParams { curve_type: CurveType::Logarithmic, end_supply: 1_000_000, growth_rate: 5000, price_increment: 0 },
+    ]
+}
+
+fn piecewise_curve(total_supply: u64) -> BondingCurve {
+    let mut curve = curve(CurveType::Piecewise, 0, total_supply, 0);
+    curve.stages = build_curve_stages(curve.initial_price, &stage_params()).unwrap();
+    curve.current_price = calculate_price_piecewise(&curve, total_supply).unwrap();
+    curve
+}
+
+fn constant_product_curve(total_supply: u64) -> BondingCurve {
+    // 1.353x virtual token reserve over a 1M max supply, opening at 1000 lamports
+    let mut curve = curve(CurveType::ConstantProduct, 13530, 0, 1000);
+    curve.virtual_token_reserves = 1_353_000;
+    curve.virtual_sol_reserves = 1_353_000 * 1000;
+
+    if total_supply > 0 {
+        let invariant = curve.virtual_sol_reserves as u128 * curve.virtual_token_reserves as u128;
+        curve.virtual_token_reserves -= total_supply;
+        curve.virtual_sol_reserves = invariant.div_ceil(curve.virtual_token_reserves as u128) as u64;
+        curve.total_supply = total_supply;
+        curve.current_price = curve.virtual_sol_reserves / curve.virtual_token_reserves;
+    }
+    curve
+}
+
+#[test]
+fn test_exponential_integral_matches_step_prices() {
+    // Two full steps: 1000 tokens at 1000 lamports, then 1000 tokens at 1010
+    let cost = calculate_exponential_integral(1000, 2000, 100).unwrap();
+    assert_eq!(cost, 1000 * 1000 + 1000 * 1010);
+
+    // Half a step is interpolated linearly
+    let cost = calculate_exponential_integral(1000, 500, 100).unwrap();
+    assert_eq!(cost, 500 * 1000);
+}
+
+#[test]
+fn test_exponential_integral_is_monotonic() {
+    let mut previous = 0;
+    for supply in (0..200_000).step_by(997) {
+        let cost = calculate_exponential_integral(1000, supply, 100).unwrap();
+        assert!(cost >= previous, "integral decreased at supply {}", supply);
+        previous = cost;
+    }
+}
+
+#[test]
+fn test_exponential_large_buy_pays_more_than_start_price() {
+    let curve = exponential_curve(0);
+    let sol_amount = 500_000_000; // 0.5 SOL
+
+    let tokens = calculate_tokens_for_sol_with_curve(sol_amount, &curve).unwrap();
+    let tokens_at_start_price = sol_amount / curve.current_price;
+
+    assert!(tokens > 0);
+    assert!(
+        tokens < tokens_at_start_price,
+        "big buy should not fill entirely at the starting price"
+    );
+}
+
+#[test]
+fn test_exponential_round_trip_never_profits() {
+    for start_supply in [0, 1, 999, 1000, 12_345, 250_000] {
+        for sol_amount in [1_000, 123_457, 10_000_000, 400_000_000] {
+            let mut curve = exponential_curve(start_supply);
+            let tokens = calculate_tokens_for_sol_with_curve(sol_amount, &curve).unwrap();
+            if tokens == 0 {
+                continue;
+            }
+
+            curve.total_supply += tokens;
+            let sol_back = calculate_sol_for_tokens_with_curve(tokens, &curve).unwrap();
+
+            assert!(
+                sol_back <= sol_amount,
+                "round trip returned {} for {} paid (supply {})",
+                sol_back,
+                sol_amount,
+                start_supply
+            );
+        }
+    }
+}
+
+#[test]
+fn test_exponential_split_sells_match_single_sell() {
+    let curve = exponential_curve(100_000);
+    let whole = calculate_sol_for_tokens_with_curve(30_000, &curve).unwrap();
+
+    let mut split_curve = exponential_curve(100_000);
+    let first = calculate_sol_for_tokens_with_curve(12_345, &split_curve).unwrap();
+    split_curve.total_supply -= 12_345;
+    let second = calculate_sol_for_tokens_with_curve(30_000 - 12_345, &split_curve).unwrap();
+
+    assert_eq!(whole, first + second);
+}
+
+#[test]
+fn test_logarithmic_price_is_monotonic_and_concave() {
+    let mut previous_price = calculate_price_logarithmic(1_000_000_000, 0, 5000).unwrap();
+    let mut previous_increase = u64::MAX;
+    assert_eq!(previous_price, 1_000_000_000);
+
+    for supply in (10_000..1_000_000).step_by(10_000) {
+        let price = calculate_price_logarithmic(1_000_000_000, supply, 5000).unwrap();
+        let increase = price - previous_price;
+        assert!(price >= previous_price, "price decreased at supply {}", supply);
+        assert!(increase <= previous_increase, "price accelerated at supply {}", supply);
+        previous_price = price;
+        previous_increase = increase;
+    }
+}
+
+#[test]
+fn test_logarithmic_price_matches_natural_log() {
+    // ln(1 + 1718/1000) ~= 1, so the price should be ~1.5x the initial price
+    let price = calculate_price_logarithmic(1_000_000, 1718, 5000).unwrap();
+    assert!((1_499_000..=1_500_000).contains(&price), "price was {}", price);
+
+    // ln(1 + 1000/1000) = ln(2)
+    let price = calculate_price_logarithmic(1_000_000, 1000, 10000).unwrap();
+    assert_eq!(price, 1_693_147);
+}
+
+#[test]
+fn test_logarithmic_integral_is_monotonic() {
+    let mut previous = 0;
+    for supply in (0..500_000).step_by(991) {
+        let cost = calculate_logarithmic_integral(1000, supply, 5000).unwrap();
+        assert!(cost >= previous, "integral decreased at supply {}", supply);
+        previous = cost;
+    }
+}
+
+#[test]
+fn test_logarithmic_buy_is_priced_above_spot() {
+    let curve = logarithmic_curve(0);
+    let sol_amount = 200_000_000;
+
+    let tokens = calculate_tokens_for_sol_with_curve(sol_amount, &curve).unwrap();
+    assert!(tokens > 0);
+    assert!(tokens < sol_amount / curve.current_price);
+}
+
+#[test]
+fn test_logarithmic_round_trip_never_profits() {
+    for start_supply in [0, 1, 999, 50_000, 400_000] {
+        for sol_amount in [1_000, 123_457, 10_000_000, 300_000_000] {
+            let mut curve = logarithmic_curve(start_supply);
+            let tokens = calculate_tokens_for_sol_with_curve(sol_amount, &curve).unwrap();
+            if tokens == 0 {
+                continue;
+            }
+
+            curve.total_supply += tokens;
+            let sol_back = calculate_sol_for_tokens_with_curve(tokens, &curve).unwrap();
+            assert!(sol_back <= sol_amount);
+        }
+    }
+}
+
+#[test]
+fn test_constant_product_buy_preserves_invariant() {
+    for start_supply in [0, 1, 100_000, 750_000] {
+        let curve = constant_product_curve(start_supply);
+        let invariant = curve.virtual_sol_reserves as u128 * curve.virtual_token_reserves as u128;
+
+        for sol_amount in [1, 999, 1_000_000, 250_000_000] {
+            let tokens = calculate_tokens_for_sol_with_curve(sol_amount, &curve).unwrap();
+            let new_invariant = (curve.virtual_sol_reserves + sol_amount) as u128
+                * (curve.virtual_token_reserves - tokens) as u128;
+            assert!(new_invariant >= invariant, "buy of {} broke x*y=k", sol_amount);
+        }
+    }
+}
+
+#[test]
+fn test_constant_product_round_trip_never_profits() {
+    for start_supply in [0, 1, 100_000, 750_000] {
+        for sol_amount in [1_000, 123_457, 10_000_000, 300_000_000] {
+            let mut curve = constant_product_curve(start_supply);
+            let tokens = calculate_tokens_for_sol_with_curve(sol_amount, &curve).unwrap();
+            if tokens == 0 {
+                continue;
+            }
+
+            curve.total_supply += tokens;
+            curve.virtual_sol_reserves += sol_amount;
+            curve.virtual_token_reserves -= tokens;
+            let sol_back = calculate_sol_for_tokens_with_curve(tokens, &curve).unwrap();
+            assert!(sol_back <= sol_amount);
+        }
+    }
+}
+
+#[test]
+fn test_constant_product_opens_at_initial_price() {
+    let curve = constant_product_curve(0);
+    assert_eq!(curve.virtual_sol_reserves / curve.virtual_token_reserves, curve.initial_price);
+
+    // A tiny buy fills at the initial price
+    let tokens = calculate_tokens_for_sol_with_curve(10_000, &curve).unwrap();
+    assert_eq!(tokens, 9);
+}
+
+#[test]
+fn test_constant_product_market_cap_and_impact_use_reserves() {
+    let curve = constant_product_curve(500_000);
+    let expected = 500_000u128 * curve.virtual_sol_reserves as u128 / curve.virtual_token_reserves as u128;
+    assert_eq!(calculate_market_cap(&curve).unwrap() as u128, expected);
+
+    let small = calculate_price_impact(1_000_000, &curve).unwrap();
+    let large = calculate_price_impact(500_000_000, &curve).unwrap();
+    assert!(small < large);
+    assert!(large > 0);
+}
+
+#[test]
+fn test_piecewise_price_is_continuous_across_stages() {
+    let curve = piecewise_curve(0);
+    assert_eq!(curve.stages.len(), 3);
+    assert_eq!(curve.stages[0].start_price, curve.initial_price);
+
+    for window in curve.stages.windows(2) {
+        let boundary = window[1].start_supply;
+        let before = calculate_price_piecewise(&curve, boundary - 1).unwrap();
+        let at = calculate_price_piecewise(&curve, boundary).unwrap();
+
+        assert_eq!(window[0].end_supply, boundary);
+        assert!(at >= before, "price dropped at breakpoint {}", boundary);
+        // One token of movement can't be worth more than a 1% jump
+        assert!(at - before <= before / 100 + 1, "price gapped at breakpoint {}", boundary);
+    }
+}
+
+#[test]
+fn test_piecewise_buy_crosses_stage_boundaries() {
+    let curve = piecewise_curve(90_000);
+    let sol_amount = 10_000_000_000;
+
+    let tokens = calculate_tokens_for_sol_with_curve(sol_amount, &curve).unwrap();
+    assert!(curve.total_supply + tokens > 400_000, "buy should reach the final stage");
+
+    // Selling the same tokens back walks down through every stage
+    let mut after = curve.clone();
+    after.total_supply += tokens;
+    let sol_back = calculate_sol_for_tokens_with_curve(tokens, &after).unwrap();
+    assert!(sol_back <= sol_amount);
+    assert!(sol_back > sol_amount / 100 * 99, "round trip lost more than rounding");
+}
+
+#[test]
+fn test_piecewise_split_sells_match_single_sell() {
+    let curve = piecewise_curve(450_000);
+    let whole = calculate_sol_for_tokens_with_curve(400_000, &curve).unwrap();
+
+    let mut split_curve = piecewise_curve(450_000);
+    let first = calculate_sol_for_tokens_with_curve(350_000, &split_curve).unwrap();
+    split_curve.total_supply -= 350_000;
+    let second = calculate_sol_for_tokens_with_curve(50_000, &split_curve).unwrap();
+
+    assert_eq!(whole, first + second);
+}
+
+#[test]
+fn test_invalid_curve_stages_are_rejected() {
+    assert!(build_curve_stages(1000, &[]).is_err());
+
+    let mut unordered = stage_params();
+    unordered[1].end_supply = 50_000;
+    assert!(build_curve_stages(1000, &unordered).is_err());
+
+    let mut nested = stage_params();
+    nested[2].curve_type = CurveType::ConstantProduct;
+    assert!(build_curve_stages(1000, &nested).is_err());
+
+    let mut flat_linear = stage_params();
+    flat_linear[0].price_increment = 0;
+    assert!(build_curve_stages(1000, &flat_linear).is_err());
+
+    let too_many = vec![stage_params()[0]; 6]
+        .iter()
+        .enumerate()
+        .map(|(i, stage)| CurveStageParams { end_supply: (i as u64 + 1) * 1000, ..*stage })
+        .collect::<Vec<_>>();
+    assert!(build_curve_stages(1000, &too_many).is_err());
+}
+
+#[test]
+fn test_exact_token_cost_buys_at_least_the_requested_amount() {
+    let curves = [
+        curve(CurveType::Linear, 0, 0, 1000),
+        exponential_curve(12_345),
+        logarithmic_curve(54_321),
+        constant_product_curve(100_000),
+        piecewise_curve(99_000),
+    ];
+
+    for curve in curves.iter() {
+        for token_amount in [1, 999, 1000, 25_000] {
+            let cost = calculate_sol_for_exact_tokens(token_amount, curve).unwrap();
+            let tokens = calculate_tokens_for_sol_with_curve(cost, curve).unwrap();
+            assert!(
+                tokens >= token_amount,
+                "{:?}: paying {} bought {} of {} tokens",
+                curve.curve_type,
+                cost,
+                tokens,
+                token_amount
+            );
+
+            // One lamport less must not be enough, otherwise the user was overcharged
+            if cost > 0 {
+                let tokens = calculate_tokens_for_sol_with_curve(cost - 1, curve).unwrap();
+                assert!(tokens < token_amount, "{:?}: cost is not minimal", curve.curve_type);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_exact_token_cost_respects_max_supply() {
+    let curve = exponential_curve(999_000);
+    assert!(calculate_sol_for_exact_tokens(1000, &curve).is_ok());
+    assert!(calculate_sol_for_exact_tokens(1001, &curve).is_err());
+}
+
+#[test]
+fn test_gross_amount_for_net_covers_fee_exactly() {
+    for net_amount in [0, 1, 99, 100, 12_345, 1_000_000_000] {
+        let gross = gross_amount_for_net(net_amount, 100).unwrap();
+        let fee = gross * 100 / 10000;
+        assert!(gross - fee >= net_amount);
+
+        if gross > 0 {
+            let smaller = gross - 1;
+            assert!(smaller - smaller * 100 / 10000 < net_amount, "gross {} is not minimal", gross);
+        }
+    }
+
+    assert!(gross_amount_for_net(1000, 10000).is_err());
+}
+
+#[test]
+fn test_exact_sol_sell_burns_the_fewest_tokens() {
+    let curves = [
+        curve(CurveType::Linear, 0, 500_000, 1000),
+        exponential_curve(500_000),
+        logarithmic_curve(500_000),
+        constant_product_curve(500_000),
+        piecewise_curve(500_000),
+    ];
+    let net = |tokens: u64, curve: &BondingCurve| {
+        let gross = calculate_sol_for_tokens_with_curve(tokens, curve).unwrap();
+        gross - calculate_transaction_fee(gross, curve).unwrap()
+    };
+
+    for curve in curves.iter() {
+        for sol_out in [1, 999_999, 10_000_000, 100_000_000] {
+            let (tokens, gross) = calculate_tokens_for_exact_sol(sol_out, curve).unwrap();
+            assert_eq!(gross, calculate_sol_for_tokens_with_curve(tokens, curve).unwrap());
+            assert!(net(tokens, curve) >= sol_out, "{:?}: {} tokens pay too little", curve.curve_type, tokens);
+            assert!(
+                tokens == 1 || net(tokens - 1, curve) < sol_out,
+                "{:?}: {} tokens is not minimal",
+                curve.curve_type,
+                tokens
+            );
+        }
+    }
+}
+
+#[test]
+fn test_exact_sol_sell_rejects_unreachable_amounts() {
+    let curve = exponential_curve(1000);
+    let gross = calculate_sol_for_tokens_with_curve(1000, &curve).unwrap();
+    assert!(calculate_tokens_for_exact_sol(gross, &curve).is_err());
+}
+
+#[test]
+fn test_buy_quote_matches_executed_buy() {
+    let curves = [
+        curve(CurveType::Linear, 0, 0, 1000),
+        exponential_curve(12_345),
+        logarithmic_curve(54_321),
+        constant_product_curve(100_000),
+        piecewise_curve(99_000),
+    ];
+
+    for curve in curves.iter() {
+        let sol_amount = 50_000_000;
+        let quote = calculate_buy_quote(sol_amount, curve, LAUNCH_WINDOW_OVER).unwrap();
+        let fee = calculate_transaction_fee(sol_amount, curve).unwrap();
+        let tokens = calculate_tokens_for_sol_with_curve(sol_amount - fee, curve).unwrap();
+
+        let mut executed = curve.clone();
+        apply_buy_to_curve(&mut executed, sol_amount - fee, tokens).unwrap();
+
+        assert_eq!(quote.token_amount, tokens);
+        assert_eq!(quote.sol_amount, sol_amount);
+        assert_eq!(quote.transaction_fee, fee);
+        assert_eq!(quote.creator_fee + quote.platform_fee, fee);
+        assert_eq!(quote.new_price, executed.current_price);
+        let price_impact = calculate_price_impact(sol_amount - fee, curve).unwrap();
+        assert_eq!(quote.price_impact_bps, price_impact);
+        assert_eq!(quote.sniper_tax, 0);
+    }
+}
+
+#[test]
+fn test_sniper_tax_decays_to_the_base_fee() {
+    // 50% total at launch over a 1% base fee
+    let curve = exponential_curve(0);
+    assert_eq!(calculate_sniper_tax_bps(&curve, 0), 4900);
+    assert_eq!(calculate_sniper_tax_bps(&curve, 900), 3675);
+    assert_eq!(calculate_sniper_tax_bps(&curve, 1800), 2450);
+    assert_eq!(calculate_sniper_tax_bps(&curve, LAUNCH_WINDOW_OVER - 1), 1);
+    assert_eq!(calculate_sniper_tax_bps(&curve, LAUNCH_WINDOW_OVER), 0);
+    // A clock behind the creation time counts as launch
+    assert_eq!(calculate_sniper_tax_bps(&curve, -60), 4900);
+
+    let mut untaxed = curve.clone();
+    untaxed.sniper_tax_start_bps = 0;
+    assert_eq!(calculate_sniper_tax_bps(&untaxed, 0), 0);
+
+    // The tax is charged on top of the fee and leaves less SOL to buy tokens with
+    let sol_amount = LAMPORTS_PER_SOL;
+    assert_eq!(calculate_sniper_tax(sol_amount, &curve, 0).unwrap(), 490_000_000);
+    let sniped = calculate_buy_quote(sol_amount, &curve, 0).unwrap();
+    let later = calculate_buy_quote(sol_amount, &curve, LAUNCH_WINDOW_OVER).unwrap();
+    assert_eq!(sniped.sniper_tax, 490_000_000);
+    assert_eq!(sniped.transaction_fee, later.transaction_fee);
+    let net = sol_amount - sniped.transaction_fee - sniped.sniper_tax;
+    assert_eq!(
+        sniped.token_amount,
+        calculate_tokens_for_sol_with_curve(net, &curve).unwrap()
+    );
+    assert!(sniped.token_amount < later.token_amount);
+}
+
+#[test]
+fn test_wallet_holding_cap_is_a_share_of_max_supply() {
+    // 2% of a 1_000_000 unit supply
+    let mut curve = exponential_curve(0);
+    assert_eq!(calculate_max_wallet_holding(&curve).unwrap(), Some(20_000));
+
+    curve.decimals = 9;
+    assert_eq!(calculate_max_wallet_holding(&curve).unwrap(), Some(20_000_000));
+
+    curve.max_wallet_holding_bps = 0;
+    assert_eq!(calculate_max_wallet_holding(&curve).unwrap(), None);
+}
+
+#[test]
+fn test_presale_proofs_verify_against_the_root() {
+    let wallets: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let allocation = LAMPORTS_PER_SOL;
+    let leaves: Vec<[u8; 32]> =
+        wallets.iter().map(|wallet| presale_leaf(wallet, allocation)).collect();
+    let parent = |a: [u8; 32], b: [u8; 32]| hashv(&[&a.min(b), &a.max(b)]).to_bytes();
+    // The third leaf pairs with the node above the first two
+    let branch = parent(leaves[0], leaves[1]);
+    let root = parent(branch, leaves[2]);
+
+    assert!(verify_presale_proof(&root, &wallets[0], allocation, &[leaves[1], leaves[2]]));
+    assert!(verify_presale_proof(&root, &wallets[1], allocation, &[leaves[0], leaves[2]]));
+    assert!(verify_presale_proof(&root, &wallets[2], allocation, &[branch]));
+
+    // A different allocation, wallet or proof doesn't match
+    assert!(!verify_presale_proof(&root, &wallets[0], 2 * allocation, &[leaves[1], leaves[2]]));
+    assert!(!verify_presale_proof(&root, &Pubkey::new_unique(), allocation, &[branch]));
+    assert!(!verify_presale_proof(&root, &wallets[2], allocation, &[]));
+
+    let mut curve = exponential_curve(0);
+    assert!(!is_presale_active(&curve, 0));
+    curve.presale_merkle_root = Some(root);
+    curve.presale_end = 600;
+    assert!(is_presale_active(&curve, 599));
+    assert!(!is_presale_active(&curve, 600));
+}
+
+#[test]
+fn test_opening_auction_fills_commits_as_one_buy() {
+    let mut curve = exponential_curve(0);
+    let committed_sol = LAMPORTS_PER_SOL / 100;
+    let (filled_sol, transaction_fee, tokens) =
+        calculate_auction_fill(committed_sol, &curve).unwrap();
+    let quote = calculate_buy_quote(committed_sol, &curve, LAUNCH_WINDOW_OVER).unwrap();
+    assert_eq!(filled_sol, committed_sol);
+    assert_eq!(transaction_fee, quote.transaction_fee);
+    assert_eq!(tokens, quote.token_amount);
+
+    // Commits of 1 and 2 parts share the fill 1:2, and nothing is refunded
+    curve.auction_committed_sol = committed_sol;
+    curve.auction_filled_sol = filled_sol;
+    curve.auction_token_amount = tokens;
+    let first_sol = committed_sol / 3;
+    let (first, first_refund) = calculate_auction_share(first_sol, &curve).unwrap();
+    let (second, second_refund) =
+        calculate_auction_share(committed_sol - first_sol, &curve).unwrap();
+    assert!(first + second <= tokens && tokens - (first + second) <= 1);
+    assert!(second >= 2 * first);
+    assert_eq!((first_refund, second_refund), (0, 0));
+
+    // Commits too small to buy a single unit are refunded in full
+    assert_eq!(calculate_auction_fill(1, &curve).unwrap(), (0, 0, 0));
+}
+
+#[test]
+fn test_opening_auction_beyond_max_supply_refunds_the_rest() {
+    let mut curve = exponential_curve(0);
+    // Twice what the whole supply costs
+    let committed_sol = 2 * calculate_sol_for_exact_tokens(curve.max_supply, &curve).unwrap();
+    let (filled_sol, transaction_fee, tokens) =
+        calculate_auction_fill(committed_sol, &curve).unwrap();
+    assert_eq!(tokens, curve.max_supply);
+    assert!(filled_sol < committed_sol);
+    assert_eq!(transaction_fee, calculate_transaction_fee(filled_sol, &curve).unwrap());
+    // The SOL left after the fee covers the remaining supply
+    let curve_cost = calculate_sol_for_exact_tokens(tokens, &curve).unwrap();
+    assert!(filled_sol - transaction_fee >= curve_cost);
+
+    curve.auction_committed_sol = committed_sol;
+    curve.auction_filled_sol = filled_sol;
+    curve.auction_token_amount = tokens;
+    let (half, refund_amount) = calculate_auction_share(committed_sol / 2, &curve).unwrap();
+    assert_eq!(half, tokens / 2);
+    assert_eq!(refund_amount, (committed_sol - filled_sol) / 2);
+    assert!(calculate_auction_share(committed_sol + 1, &curve).is_err());
+}
+
+#[test]
+fn test_sell_quote_matches_executed_sell() {
+    let curves = [
+        curve(CurveType::Linear, 0, 500_000, 1000),
+        exponential_curve(500_000),
+        logarithmic_curve(500_000),
+        constant_product_curve(500_000),
+        piecewise_curve(500_000),
+    ];
+
+    for curve in curves.iter() {
+        let token_amount = 20_000;
+        let quote = calculate_sell_quote(token_amount, curve).unwrap();
+        let gross = calculate_sol_for_tokens_with_curve(token_amount, curve).unwrap();
+        let fee = calculate_transaction_fee(gross, curve).unwrap();
+
+        let mut executed = curve.clone();
+        apply_sell_to_curve(&mut executed, gross, token_amount).unwrap();
+
+        assert_eq!(quote.token_amount, token_amount);
+        assert_eq!(quote.sol_amount, gross - fee);
+        assert_eq!(quote.transaction_fee, fee);
+        assert_eq!(quote.new_price, executed.current_price);
+        assert!(quote.new_price <= curve.current_price);
+    }
+
+    assert!(calculate_sell_quote(500_001, &exponential_curve(500_000)).is_err());
+}
+
+#[test]
+fn test_fee_split_adds_up_to_the_fee() {
+    let curve = exponential_curve(0);
+    for fee in [0, 1, 3, 999, 1_000_001] {
+        let (creator_fee, platform_fee) = split_transaction_fee(fee, &curve).unwrap();
+        assert_eq!(creator_fee + platform_fee, fee);
+        assert!(creator_fee <= platform_fee);
+
+        // A referrer takes referral_fee_bps of the 100 bps fee out of the platform's half
+        let referral_fee = calculate_referral_fee(fee, &curve).unwrap();
+        assert_eq!(referral_fee, fee / 5);
+        assert!(referral_fee <= platform_fee);
+    }
+
+    let mut unreferred = exponential_curve(0);
+    unreferred.referral_fee_bps = 0;
+    assert_eq!(calculate_referral_fee(1_000_001, &unreferred).unwrap(), 0);
+}
+
+#[test]
+fn test_sell_price_impact_grows_with_size() {
+    let curves = [
+        curve(CurveType::Linear, 0, 500_000, 51_000), // 1000 + 100 per 1000 tokens
+        exponential_curve(500_000),
+        logarithmic_curve(500_000),
+        constant_product_curve(500_000),
+        piecewise_curve(500_000),
+    ];
+
+    for curve in curves.iter() {
+        let small = calculate_sell_price_impact(1_000, curve).unwrap();
+        let large = calculate_sell_price_impact(400_000, curve).unwrap();
+        assert!(small <= large, "{:?}: impact is not monotonic", curve.curve_type);
+        assert!(large > curve.max_sell_price_impact_bps, "{:?}: large dump passes the limit", curve.curve_type);
+        assert!(small <= curve.max_sell_price_impact_bps, "{:?}: small sell is blocked", curve.curve_type);
+        assert_eq!(calculate_sell_quote(1_000, curve).unwrap().price_impact_bps, small);
+    }
+
+    assert!(calculate_sell_price_impact(500_001, &exponential_curve(500_000)).is_err());
+}
+
+#[test]
+fn test_curve_units_are_decimals_independent() {
+    // One whole token is 10^6 curve units at any decimals
+    for decimals in 0..=9u8 {
+        let one_token = 10u64.pow(decimals as u32);
+        assert_eq!(to_curve_units(one_token, decimals).unwrap(), 1_000_000);
+        assert_eq!(to_raw_units(1_000_000, decimals).unwrap(), one_token);
+    }
+
+    // Sub-unit dust rounds down in both directions
+    assert_eq!(to_curve_units(1_999, 9).unwrap(), 1);
+    assert_eq!(to_raw_units(1_999_999, 0).unwrap(), 1);
+}
+
+#[test]
+fn test_quotes_report_raw_amounts_for_other_decimals() {
+    let six = exponential_curve(500_000);
+    let mut nine = six.clone();
+    nine.decimals = 9;
+
+    // Same SOL buys the same share of a token whatever its decimals
+    let buy_six = calculate_buy_quote(50_000_000, &six, LAUNCH_WINDOW_OVER).unwrap();
+    let buy_nine = calculate_buy_quote(50_000_000, &nine, LAUNCH_WINDOW_OVER).unwrap();
+    assert_eq!(buy_nine.token_amount, buy_six.token_amount * 1000);
+    assert_eq!(buy_nine.new_price, buy_six.new_price);
+
+    let sell_six = calculate_sell_quote(20_000, &six).unwrap();
+    let sell_nine = calculate_sell_quote(20_000_999, &nine).unwrap();
+    assert_eq!(sell_nine.token_amount, 20_000_000);
+    assert_eq!(sell_nine.sol_amount, sell_six.sol_amount);
+}
+
+#[test]
+fn test_pool_opens_at_the_curve_closing_price() {
+    let mut curves = [
+        exponential_curve(500_000),
+        logarithmic_curve(500_000),
+        constant_product_curve(500_000),
+        piecewise_curve(500_000),
+    ];
+
+    for curve in curves.iter_mut() {
+        curve.treasury_balance = 100 * LAMPORTS_PER_SOL;
+        let (sol_amount, _, _) = calculate_graduation_split(curve).unwrap();
+
+        let token_amount = calculate_pool_token_amount(sol_amount, curve).unwrap();
+        assert!(token_amount <= curve.max_supply - curve.total_supply);
+
+        // Uncapped pools price a token within one lamport of the curve
+        if token_amount < curve.max_supply - curve.total_supply {
+            let pool_price = sol_amount / token_amount;
+            let curve_price = calculate_market_cap(curve).unwrap() / curve.total_supply;
+            assert!(pool_price.abs_diff(curve_price) <= 1, "{:?}: pool opens off-curve", curve.curve_type);
+        }
+    }
+}
+
+#[test]
+fn test_pool_token_amount_is_capped_at_unminted_supply() {
+    let mut curve = exponential_curve(999_000);
+    curve.treasury_balance = 1_000 * LAMPORTS_PER_SOL;
+    assert_eq!(calculate_pool_token_amount(850 * LAMPORTS_PER_SOL, &curve).unwrap(), 1_000);
+}
+
+#[test]
+fn test_initial_lp_amount_is_geometric_mean() {
+    assert_eq!(calculate_initial_lp_amount(0, 1_000).unwrap(), 0);
+    assert_eq!(calculate_initial_lp_amount(1, 1).unwrap(), 1);
+    assert_eq!(calculate_initial_lp_amount(4, 9).unwrap(), 6);
+    assert_eq!(calculate_initial_lp_amount(10, 10).unwrap(), 10);
+    assert_eq!(calculate_initial_lp_amount(u64::MAX, u64::MAX).unwrap(), u64::MAX);
+
+    let lp = calculate_initial_lp_amount(85 * LAMPORTS_PER_SOL, 123_456_789).unwrap() as u128;
+    let product = 85 * LAMPORTS_PER_SOL as u128 * 123_456_789;
+    assert!(lp * lp <= product && (lp + 1) * (lp + 1) > product);
+}
+
+#[test]
+fn test_pool_swap_keeps_the_invariant_and_charges_a_fee() {
+    let (sol_reserves, token_reserves) = (85 * LAMPORTS_PER_SOL, 200_000_000_000);
+    let k = sol_reserves as u128 * token_reserves as u128;
+
+    for amount_in in [1_000, LAMPORTS_PER_SOL, 10 * LAMPORTS_PER_SOL] {
+        let tokens_out = calculate_pool_swap_output(amount_in, sol_reserves, token_reserves).unwrap();
+        let new_k = (sol_reserves + amount_in) as u128 * (token_reserves - tokens_out) as u128;
+        assert!(new_k >= k);
+
+        // Selling straight back returns less than was paid
+        let sol_back = calculate_pool_swap_output(
+            tokens_out,
+            token_reserves - tokens_out,
+            sol_reserves + amount_in,
+        ).unwrap();
+        assert!(sol_back < amount_in);
+    }
+
+    assert_eq!(calculate_pool_swap_output(1_000, 0, 0).unwrap(), 0);
+}
+
+#[test]
+fn test_graduation_split_is_85_10_5_and_exact() {
+    let mut curve = exponential_curve(500_000);
+    curve.treasury_balance = 100 * LAMPORTS_PER_SOL;
+    assert_eq!(
+        calculate_graduation_split(&curve).unwrap(),
+        (85 * LAMPORTS_PER_SOL, 10 * LAMPORTS_PER_SOL, 5 * LAMPORTS_PER_SOL)
+    );
+
+    // Rounding dust goes to the creator so nothing is left behind
+    curve.treasury_balance = 12_345_678_901;
+    let (liquidity, platform, creator) = calculate_graduation_split(&curve).unwrap();
+    assert_eq!(liquidity + platform + creator, curve.treasury_balance);
+
+    // Shares that don't cover the whole treasury are rejected
+    curve.graduation_creator_bps = 400;
+    assert!(calculate_graduation_split(&curve).is_err());
+}
+
+#[test]
+fn test_migration_layouts_point_at_distinct_accounts() {
+    let mints = [Pubkey::new_from_array([0; 32]), Pubkey::new_from_array([255; 32])];
+    for mint in mints {
+        for target in [MigrationTarget::RaydiumAmmV4, MigrationTarget::RaydiumCpmm] {
+            let layout = target.dex_account_layout(&mint).unwrap();
+            let mut indices = vec![
+                layout.payer,
+                layout.token_mint,
+                layout.sol_mint,
+                layout.token_account,
+                layout.sol_account,
+                layout.lp_mint,
+                layout.lp_account,
+            ];
+            assert!(indices.iter().all(|&index| index < layout.account_count));
+            indices.sort();
+            indices.dedup();
+            assert_eq!(indices.len(), 7);
+        }
+    }
+
+    // CP-Swap orders the pair by mint address
+    let low = MigrationTarget::RaydiumCpmm.dex_account_layout(&mints[0]).unwrap();
+    let high = MigrationTarget::RaydiumCpmm.dex_account_layout(&mints[1]).unwrap();
+    assert!(low.token_mint < low.sol_mint);
+    assert!(high.token_mint > high.sol_mint);
+
+    assert!(MigrationTarget::InHousePool.dex_account_layout(&mints[0]).is_none());
+    assert!(MigrationTarget::InHousePool.program_id().is_none());
+}
+
+#[test]
+fn test_migration_instructions_encode_each_dex() {
+    let accounts = vec![AccountMeta::new(Pubkey::new_unique(), true)];
+    let mint = Pubkey::new_unique();
+    let (token_amount, sol_amount, open_time) = (5_000_000u64, 84 * LAMPORTS_PER_SOL, 1_700_000_000u64);
+
+    // Raydium AMM v4 initialize2: tag, nonce, open_time, pc (SOL), coin (token)
+    let ix = build_migration_instruction(
+        MigrationTarget::RaydiumAmmV4, &mint, accounts.clone(), token_amount, sol_amount, open_time,
+    ).unwrap();
+    let (_, nonce) = Pubkey::find_program_address(&[b"amm authority"], &RAYDIUM_AMM_V4_PROGRAM_ID);
+    let mut expected = vec![1u8, nonce];
+    expected.extend_from_slice(&open_time.to_le_bytes());
+    expected.extend_from_slice(&sol_amount.to_le_bytes());
+    expected.extend_from_slice(&token_amount.to_le_bytes());
+    assert_eq!(ix.program_id, RAYDIUM_AMM_V4_PROGRAM_ID);
+    assert_eq!(ix.accounts, accounts);
+    assert_eq!(ix.data, expected);
+
+    // Raydium CP-Swap initialize: amounts follow the pair's mint order
+    let discriminator = &hash(b"global:initialize").to_bytes()[..8];
+    for (mint, token_first) in [
+        (Pubkey::new_from_array([0; 32]), true),
+        (Pubkey::new_from_array([255; 32]), false),
+    ] {
+        assert_eq!(mint < native_mint::ID, token_first);
+        let ix = build_migration_instruction(
+            MigrationTarget::RaydiumCpmm, &mint, accounts.clone(), token_amount, sol_amount, open_time,
+        ).unwrap();
+        let (amount_0, amount_1) = if token_first {
+            (token_amount, sol_amount)
+        } else {
+            (sol_amount, token_amount)
+        };
+        let mut expected = discriminator.to_vec();
+        expected.extend_from_slice(&amount_0.to_le_bytes());
+        expected.extend_from_slice(&amount_1.to_le_bytes());
+        expected.extend_from_slice(&open_time.to_le_bytes());
+        assert_eq!(ix.program_id, RAYDIUM_CPMM_PROGRAM_ID);
+        assert_eq!(ix.data, expected);
+    }
+
+    // The in-house pool is created by create_raydium_pool, not a CPI
+    assert!(build_migration_instruction(
+        MigrationTarget::InHousePool, &mint, accounts, token_amount, sol_amount, open_time,
+    ).is_err());
+}
+
+#[test]
+fn test_refund_mode_starts_after_a_missed_deadline() {
+    let mut curve = exponential_curve(500_000);
+    assert!(!is_refund_active(&curve, i64::MAX));
+
+    curve.graduation_deadline = Some(1_000);
+    assert!(!is_refund_active(&curve, 1_000));
+    assert!(is_refund_active(&curve, 1_001));
+
+    // Graduated curves never refund
+    curve.is_graduated = true;
+    assert!(!is_refund_active(&curve, 1_001));
+}
+
+#[test]
+fn test_refunds_are_pro_rata_in_any_order() {
+    let mut curve = exponential_curve(500_000);
+    curve.curve_sol_balance = 100 * LAMPORTS_PER_SOL + 7;
+    let holdings = [123_457u64, 250_000, 126_543];
+
+    let refund_all = |order: &[u64]| {
+        let mut curve = curve.clone();
+        order
+            .iter()
+            .map(|&tokens| {
+                let refund = calculate_refund_amount(tokens, &curve).unwrap();
+                curve.curve_sol_balance -= refund;
+                curve.total_supply -= tokens;
+                (tokens, refund)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let forward = refund_all(&holdings);
+    let mut backward = refund_all(&[holdings[2], holdings[1], holdings[0]]);
+    backward.reverse();
+
+    // The last holder sweeps the rounding dust, so the curve's SOL empties exactly
+    let total: u64 = forward.iter().map(|(_, refund)| refund).sum();
+    assert_eq!(total, curve.curve_sol_balance);
+    for ((tokens, a), (_, b)) in forward.iter().zip(backward.iter()) {
+        let fair = curve.curve_sol_balance as u128 * *tokens as u128 / curve.total_supply as u128;
+        // Off by at most a lamport per earlier claim
+        assert!(a.abs_diff(*b) <= holdings.len() as u64);
+        assert!((*a as u128).abs_diff(fair) <= holdings.len() as u128);
+    }
+
+    assert!(calculate_refund_amount(curve.total_supply + 1, &curve).is_err());
+}
+
+#[test]
+fn test_refunds_leave_the_creation_fee_in_the_treasury() {
+    let mut curve = exponential_curve(500_000);
+    curve.curve_sol_balance = 40 * LAMPORTS_PER_SOL;
+    curve.treasury_balance = curve.curve_sol_balance + curve.creation_fee;
+
+    // Burning the whole supply returns only the SOL its buys put in
+    let refund = calculate_refund_amount(curve.total_supply, &curve).unwrap();
+    assert_eq!(refund, curve.curve_sol_balance);
+    assert_eq!(curve.treasury_balance - refund, curve.creation_fee);
+}
+
+fn config_params() -> ConfigParams {
+    ConfigParams {
+        platform_treasury: Pubkey::new_unique(),
+        graduation_threshold: GRADUATION_THRESHOLD,
+        creation_fee: 20_000_000,
+        transaction_fee_bps: 100,
+        creator_fee_bps: 50,
+        platform_fee_bps: 50,
+        launch_protection_period: 3600,
+        max_buy_during_protection: LAMPORTS_PER_SOL,
+        transaction_cooldown: 30,
+        max_price_impact_bps: 500,
+        max_sell_price_impact_bps: 500,
+        graduation_liquidity_bps: 8500,
+        graduation_platform_bps: 1000,
+        graduation_creator_bps: 500,
+        keeper_reward: 10_000_000,
+        referral_fee_bps: 20,
+        sniper_tax_start_bps: 5000,
+        sniper_tax_recipient: SniperTaxRecipient::Treasury,
+        max_wallet_buy_during_protection: 5 * LAMPORTS_PER_SOL,
+        max_wallet_holding_bps: 200,
+    }
+}
+
+#[test]
+fn test_config_params_are_range_checked() {
+    assert!(validate_config_params(&config_params()).is_ok());
+
+    let invalid: [fn(&mut ConfigParams); 14] = [
+        |params| params.platform_treasury = Pubkey::default(),
+        |params| params.graduation_threshold = 0,
+        // The liquidity share must cover the DEX migration budget
+        |params| params.graduation_threshold = DEX_MIGRATION_BUDGET,
+        |params| params.creation_fee = 2 * LAMPORTS_PER_SOL,
+        |params| {
+            params.transaction_fee_bps = MAX_TRANSACTION_FEE_BPS + 2;
+            params.creator_fee_bps = MAX_TRANSACTION_FEE_BPS / 2 + 1;
+            params.platform_fee_bps = MAX_TRANSACTION_FEE_BPS / 2 + 1;
+        },
+        // Creator and platform fees must add up to the transaction fee
+        |params| params.creator_fee_bps = 60,
+        // Referrers are paid out of the platform's part
+        |params| params.referral_fee_bps = 60,
+        |params| params.launch_protection_period = -1,
+        |params| params.max_sell_price_impact_bps = 0,
+        |params| params.graduation_creator_bps = 400,
+        |params| params.keeper_reward = LAMPORTS_PER_SOL,
+        |params| params.sniper_tax_start_bps = MAX_SNIPER_TAX_BPS + 1,
+        // The wallet total can't be below what a single buy may spend
+        |params| params.max_wallet_buy_during_protection = LAMPORTS_PER_SOL / 2,
+        |params| params.max_wallet_holding_bps = 10001,
+    ];
+    for update in invalid {
+        let mut params = config_params();
+        update(&mut params);
+        assert!(validate_config_params(&params).is_err());
+    }
+}
@@ -0,0 +1,3135 @@
+use anchor_lang::{prelude::*, InstructionData, ToAccountMetas};
+use anchor_lang::solana_program::{
+    entrypoint::ProgramResult,
+    hash::{hash, Hash},
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program::invoke_signed,
+    program_pack::Pack,
+    system_program,
+};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token::{self, native_mint};
+use degenie_token_creator::{
+    accounts, calculate_auction_fill, calculate_auction_share, calculate_buy_quote,
+    calculate_price_exponential, calculate_price_impact, calculate_referral_fee,
+    calculate_refund_amount, calculate_sell_quote, calculate_sol_for_exact_tokens,
+    calculate_sol_for_tokens_with_curve, calculate_tokens_for_exact_sol, calculate_transaction_fee,
+    gross_amount_for_net, instruction, presale_leaf, to_raw_units, AuctionCommit, AuctionEscrow,
+    BondingCurve, Config, ConfigParams, CurveType, MigrationTarget, PoolState, PresaleProof,
+    Referrer, SniperTaxRecipient, TokenCreatorError, TradeQuote, Treasury, UserTracker,
+    DEX_MIGRATION_BUDGET, LAMPORTS_PER_SOL, RAYDIUM_AMM_V4_PROGRAM_ID, RAYDIUM_CPMM_PROGRAM_ID,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+
+const TOTAL_SUPPLY: u64 = 500_000;
+const TREASURY_BALANCE: u64 = 100 * LAMPORTS_PER_SOL;
+// Part of the treasury, but not of the SOL backing the curve
+const CREATION_FEE: u64 = 20_000_000;
+// Below the curve's ~72 SOL market cap so it can graduate
+const GRADUATION_THRESHOLD: u64 = 50 * LAMPORTS_PER_SOL;
+const KEEPER_REWARD: u64 = 10_000_000;
+
+struct PoolAccounts {
+    mint: Pubkey,
+    creator: Pubkey,
+    admin: Pubkey,
+    config: Pubkey,
+    platform_treasury: Pubkey,
+    bonding_curve: Pubkey,
+    treasury: Pubkey,
+    auction_escrow: Pubkey,
+    pool_state: Pubkey,
+    token_vault: Pubkey,
+    sol_vault: Pubkey,
+    lp_mint: Pubkey,
+    treasury_lp_account: Pubkey,
+}
+
+impl PoolAccounts {
+    fn new(mint: Pubkey) -> Self {
+        let pda = |seed: &[u8]| {
+            Pubkey::find_program_address(&[seed, mint.as_ref()], &degenie_token_creator::ID).0
+        };
+        let treasury = pda(b"treasury");
+        let lp_mint = pda(b"lp_mint");
+        Self {
+            mint,
+            creator: Pubkey::new_unique(),
+            admin: Pubkey::new_unique(),
+            config: Pubkey::find_program_address(&[b"config"], &degenie_token_creator::ID).0,
+            platform_treasury: Pubkey::new_unique(),
+            bonding_curve: pda(b"bonding_curve"),
+            treasury,
+            auction_escrow: pda(b"auction_escrow"),
+            pool_state: pda(b"pool"),
+            token_vault: pda(b"pool_token_vault"),
+            sol_vault: pda(b"pool_sol_vault"),
+            lp_mint,
+            treasury_lp_account: get_associated_token_address(&treasury, &lp_mint),
+        }
+    }
+}
+
+fn config_params(platform_treasury: Pubkey) -> ConfigParams {
+    ConfigParams {
+        platform_treasury,
+        graduation_threshold: GRADUATION_THRESHOLD,
+        creation_fee: CREATION_FEE,
+        transaction_fee_bps: 100,
+        creator_fee_bps: 50,
+        platform_fee_bps: 50,
+        launch_protection_period: 3600,
+        max_buy_during_protection: LAMPORTS_PER_SOL,
+        transaction_cooldown: 30,
+        max_price_impact_bps: 500,
+        max_sell_price_impact_bps: 500,
+        graduation_liquidity_bps: 8500,
+        graduation_platform_bps: 1000,
+        graduation_creator_bps: 500,
+        keeper_reward: KEEPER_REWARD,
+        referral_fee_bps: 20,
+        sniper_tax_start_bps: 5000,
+        sniper_tax_recipient: SniperTaxRecipient::Treasury,
+        max_wallet_buy_during_protection: 5 * LAMPORTS_PER_SOL,
+        max_wallet_holding_bps: 200,
+    }
+}
+
+fn program_account<T: AccountSerialize>(data: &T, space: usize) -> Account {
+    let mut bytes = Vec::with_capacity(space);
+    data.try_serialize(&mut bytes).unwrap();
+    bytes.resize(space, 0);
+    Account {
+        lamports: Rent::default().minimum_balance(space),
+        data: bytes,
+        owner: degenie_token_creator::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn packed_account<T: Pack>(state: T, owner: Pubkey) -> Account {
+    let mut data = vec![0; T::LEN];
+    state.pack_into_slice(&mut data);
+    Account {
+        lamports: Rent::default().minimum_balance(T::LEN),
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Anchor's entrypoint ties the account slice to the accounts' own
+/// lifetime, which builtin processors don't promise; the bank keeps the
+/// accounts alive for the whole instruction, so the lifetimes can be joined
+fn process_instruction<'a, 'b, 'c, 'd>(
+    program_id: &'a Pubkey,
+    accounts: &'b [AccountInfo<'c>],
+    data: &'d [u8],
+) -> ProgramResult {
+    let accounts = unsafe { std::mem::transmute::<&[AccountInfo], &[AccountInfo]>(accounts) };
+    degenie_token_creator::entry(program_id, accounts, data)
+}
+
+/// Program test preloaded with a curve that has already sold TOTAL_SUPPLY
+/// tokens and holds TREASURY_BALANCE lamports, adjusted by `configure`
+fn pool_program_test(
+    keys: &PoolAccounts,
+    configure: impl FnOnce(&mut BondingCurve),
+) -> ProgramTest {
+    let mut program_test = ProgramTest::new(
+        "degenie_token_creator",
+        degenie_token_creator::ID,
+        processor!(process_instruction),
+    );
+
+    let (_, curve_bump) = Pubkey::find_program_address(
+        &[b"bonding_curve", keys.mint.as_ref()],
+        &degenie_token_creator::ID,
+    );
+    let (_, treasury_bump) = Pubkey::find_program_address(
+        &[b"treasury", keys.mint.as_ref()],
+        &degenie_token_creator::ID,
+    );
+
+    let mut bonding_curve = BondingCurve {
+        mint: keys.mint,
+        current_price: calculate_price_exponential(1000, TOTAL_SUPPLY, 100).unwrap(),
+        price_increment: 100,
+        total_supply: TOTAL_SUPPLY,
+        max_supply: 1_000_000,
+        authority: keys.creator,
+        bump: curve_bump,
+        initial_price: 1000,
+        curve_type: CurveType::Exponential,
+        growth_rate: 100,
+        treasury_balance: TREASURY_BALANCE,
+        total_volume: TREASURY_BALANCE,
+        graduation_threshold: GRADUATION_THRESHOLD,
+        is_graduated: false,
+        creation_fee: CREATION_FEE,
+        transaction_fee_bps: 100,
+        creator_fee_bps: 50,
+        platform_fee_bps: 50,
+        creation_timestamp: 0,
+        launch_protection_period: 3600,
+        max_buy_during_protection: 1_000_000_000,
+        transaction_cooldown: 30,
+        max_price_impact_bps: 500,
+        virtual_sol_reserves: 0,
+        virtual_token_reserves: 0,
+        stages: vec![],
+        max_sell_price_impact_bps: 500,
+        decimals: 6,
+        graduation_liquidity_bps: 8500,
+        graduation_platform_bps: 1000,
+        graduation_creator_bps: 500,
+        migration_target: MigrationTarget::InHousePool,
+        is_migrated: false,
+        graduation_platform_payout: 0,
+        graduation_creator_payout: 0,
+        graduation_keeper_reward: 0,
+        graduation_deadline: None,
+        paused: false,
+        creator_fees_owed: 0,
+        platform_fees_owed: 0,
+        referral_fee_bps: 20,
+        sniper_tax_start_bps: 5000,
+        sniper_tax_recipient: SniperTaxRecipient::Treasury,
+        max_wallet_buy_during_protection: 5 * LAMPORTS_PER_SOL,
+        max_wallet_holding_bps: 200,
+        presale_merkle_root: None,
+        presale_end: 0,
+        opening_auction_end: 0,
+        auction_committed_sol: 0,
+        auction_filled_sol: 0,
+        auction_token_amount: 0,
+        auction_settled: false,
+        curve_sol_balance: TREASURY_BALANCE - CREATION_FEE,
+    };
+    configure(&mut bonding_curve);
+    program_test.add_account(
+        keys.bonding_curve,
+        program_account(&bonding_curve, 8 + BondingCurve::INIT_SPACE),
+    );
+
+    let (_, config_bump) =
+        Pubkey::find_program_address(&[b"config"], &degenie_token_creator::ID);
+    let params = config_params(keys.platform_treasury);
+    let config = Config {
+        admin: keys.admin,
+        bump: config_bump,
+        platform_treasury: params.platform_treasury,
+        graduation_threshold: params.graduation_threshold,
+        creation_fee: params.creation_fee,
+        transaction_fee_bps: params.transaction_fee_bps,
+        creator_fee_bps: params.creator_fee_bps,
+        platform_fee_bps: params.platform_fee_bps,
+        launch_protection_period: params.launch_protection_period,
+        max_buy_during_protection: params.max_buy_during_protection,
+        transaction_cooldown: params.transaction_cooldown,
+        max_price_impact_bps: params.max_price_impact_bps,
+        max_sell_price_impact_bps: params.max_sell_price_impact_bps,
+        graduation_liquidity_bps: params.graduation_liquidity_bps,
+        graduation_platform_bps: params.graduation_platform_bps,
+        graduation_creator_bps: params.graduation_creator_bps,
+        keeper_reward: params.keeper_reward,
+        paused: false,
+        referral_fee_bps: params.referral_fee_bps,
+        sniper_tax_start_bps: params.sniper_tax_start_bps,
+        sniper_tax_recipient: params.sniper_tax_recipient,
+        max_wallet_buy_during_protection: params.max_wallet_buy_during_protection,
+        max_wallet_holding_bps: params.max_wallet_holding_bps,
+    };
+    program_test.add_account(keys.config, program_account(&config, 8 + Config::INIT_SPACE));
+
+    let treasury = Treasury {
+        authority: bonding_curve.authority,
+        total_collected: TREASURY_BALANCE,
+        bump: treasury_bump,
+    };
+    let mut treasury_account = program_account(&treasury, 8 + Treasury::INIT_SPACE);
+    treasury_account.lamports += TREASURY_BALANCE;
+    program_test.add_account(keys.treasury, treasury_account);
+
+    program_test.add_account(
+        keys.mint,
+        packed_account(
+            spl_token::state::Mint {
+                mint_authority: Some(keys.bonding_curve).into(),
+                supply: to_raw_units(bonding_curve.total_supply, bonding_curve.decimals).unwrap(),
+                decimals: bonding_curve.decimals,
+                is_initialized: true,
+                freeze_authority: None.into(),
+            },
+            spl_token::ID,
+        ),
+    );
+
+    program_test
+}
+
+fn graduated(curve: &mut BondingCurve) {
+    curve.is_graduated = true;
+}
+
+fn graduated_to(migration_target: MigrationTarget) -> impl FnOnce(&mut BondingCurve) {
+    move |curve| {
+        curve.is_graduated = true;
+        curve.migration_target = migration_target;
+    }
+}
+
+fn create_pool_instruction(keys: &PoolAccounts, payer: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::CreateRaydiumPool {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            pool_state: keys.pool_state,
+            treasury: keys.treasury,
+            token_vault: keys.token_vault,
+            sol_vault: keys.sol_vault,
+            lp_mint: keys.lp_mint,
+            treasury_lp_account: keys.treasury_lp_account,
+            config: keys.config,
+            creator: keys.creator,
+            platform_treasury: keys.platform_treasury,
+            keeper: *payer,
+            token_program: spl_token::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::CreateRaydiumPool {}.data(),
+    }
+}
+
+fn graduate_instruction(keys: &PoolAccounts, keeper: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::GraduateToRaydium {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            config: keys.config,
+            treasury: keys.treasury,
+            keeper: *keeper,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::GraduateToRaydium {}.data(),
+    }
+}
+
+fn swap_instruction(
+    keys: &PoolAccounts,
+    user: &Pubkey,
+    user_token_account: &Pubkey,
+    amount_in: u64,
+    sol_to_token: bool,
+) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::SwapOnPool {
+            pool_state: keys.pool_state,
+            mint: keys.mint,
+            token_vault: keys.token_vault,
+            sol_vault: keys.sol_vault,
+            bonding_curve: keys.bonding_curve,
+            config: keys.config,
+            user: *user,
+            user_token_account: *user_token_account,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::SwapOnPool {
+            amount_in,
+            min_amount_out: 1,
+            sol_to_token,
+        }
+        .data(),
+    }
+}
+
+async fn token_balance(banks_client: &mut BanksClient, address: Pubkey) -> u64 {
+    let account = banks_client.get_account(address).await.unwrap().unwrap();
+    spl_token::state::Account::unpack(&account.data).unwrap().amount
+}
+
+async fn pool_state(banks_client: &mut BanksClient, address: Pubkey) -> PoolState {
+    let account = banks_client.get_account(address).await.unwrap().unwrap();
+    PoolState::try_deserialize(&mut account.data.as_ref()).unwrap()
+}
+
+async fn bonding_curve(banks_client: &mut BanksClient, address: Pubkey) -> BondingCurve {
+    let account = banks_client.get_account(address).await.unwrap().unwrap();
+    BondingCurve::try_deserialize(&mut account.data.as_ref()).unwrap()
+}
+
+#[tokio::test]
+async fn test_pool_creation_migrates_treasury_liquidity() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let (mut banks_client, payer, recent_blockhash) =
+        pool_program_test(&keys, graduated).start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[create_pool_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The keeper reward comes off the top; the rest is split
+    let split_balance = TREASURY_BALANCE - KEEPER_REWARD;
+    let pool = pool_state(&mut banks_client, keys.pool_state).await;
+    let liquidity = split_balance * 85 / 100;
+    assert!(pool.is_initialized);
+    assert_eq!(pool.sol_reserves, liquidity);
+    assert!(pool.token_reserves > 0);
+    assert!(pool.lp_supply > 0);
+
+    // Reserves are backed by real balances
+    let sol_vault = banks_client.get_account(keys.sol_vault).await.unwrap().unwrap();
+    assert_eq!(sol_vault.lamports, liquidity);
+    assert_eq!(token_balance(&mut banks_client, keys.token_vault).await, pool.token_reserves);
+
+    // Liquidity is permanent: every LP token was burned
+    assert_eq!(pool.lp_burned, pool.lp_supply);
+    assert_eq!(token_balance(&mut banks_client, keys.treasury_lp_account).await, 0);
+    let lp_mint = banks_client.get_account(keys.lp_mint).await.unwrap().unwrap();
+    assert_eq!(spl_token::state::Mint::unpack(&lp_mint.data).unwrap().supply, 0);
+
+    // The rest of the treasury is split 10% platform and 5% creator,
+    // leaving only rent behind
+    let treasury = banks_client.get_account(keys.treasury).await.unwrap().unwrap();
+    let treasury_rent = Rent::default().minimum_balance(8 + Treasury::INIT_SPACE);
+    assert_eq!(treasury.lamports, treasury_rent);
+
+    let platform_payout = split_balance / 10;
+    let creator_payout = split_balance - liquidity - platform_payout;
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert!(curve.is_migrated);
+    assert_eq!(curve.graduation_platform_payout, platform_payout);
+    assert_eq!(curve.graduation_creator_payout, creator_payout);
+    assert_eq!(curve.graduation_keeper_reward, KEEPER_REWARD);
+    let platform = banks_client.get_account(keys.platform_treasury).await.unwrap().unwrap();
+    assert_eq!(platform.lamports, platform_payout);
+    let creator = banks_client.get_account(keys.creator).await.unwrap().unwrap();
+    assert_eq!(creator.lamports, creator_payout);
+
+    // A second migration is rejected
+    let mut transaction = Transaction::new_with_payer(
+        &[create_pool_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], banks_client.get_latest_blockhash().await.unwrap());
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_pool_creation_requires_graduation() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let (mut banks_client, payer, recent_blockhash) =
+        pool_program_test(&keys, |_| {}).start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[create_pool_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_graduation_is_a_permissionless_crank() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let keeper = Keypair::new();
+    let keeper_funds = LAMPORTS_PER_SOL;
+
+    let mut program_test = pool_program_test(&keys, |_| {});
+    program_test.add_account(
+        keeper.pubkey(),
+        Account::new(keeper_funds, 0, &system_program::ID),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // A keeper unrelated to the creator graduates and migrates the curve
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            graduate_instruction(&keys, &keeper.pubkey()),
+            create_pool_instruction(&keys, &keeper.pubkey()),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &keeper], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert!(pool_state(&mut banks_client, keys.pool_state).await.is_initialized);
+
+    // The keeper paid rent for the pool accounts and collected a reward for
+    // each crank
+    let rent = Rent::default();
+    let rent_paid = rent.minimum_balance(8 + PoolState::INIT_SPACE)
+        + 2 * rent.minimum_balance(spl_token::state::Account::LEN)
+        + rent.minimum_balance(spl_token::state::Mint::LEN);
+    let keeper_account = banks_client.get_account(keeper.pubkey()).await.unwrap().unwrap();
+    assert_eq!(keeper_account.lamports, keeper_funds - rent_paid + 2 * KEEPER_REWARD);
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert_eq!(curve.graduation_keeper_reward, 2 * KEEPER_REWARD);
+}
+
+#[tokio::test]
+async fn test_keepers_are_paid_without_a_platform_share() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let keeper = Keypair::new();
+    let keeper_funds = LAMPORTS_PER_SOL;
+
+    let mut program_test = pool_program_test(&keys, |curve| {
+        curve.graduation_liquidity_bps = 9000;
+        curve.graduation_platform_bps = 0;
+        curve.graduation_creator_bps = 1000;
+    });
+    program_test.add_account(
+        keeper.pubkey(),
+        Account::new(keeper_funds, 0, &system_program::ID),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[graduate_instruction(&keys, &keeper.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &keeper], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert_eq!(
+        banks_client.get_balance(keeper.pubkey()).await.unwrap(),
+        keeper_funds + KEEPER_REWARD
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[create_pool_instruction(&keys, &keeper.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &keeper], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Both rewards came out of the treasury, and the platform got nothing
+    let rent = Rent::default();
+    let rent_paid = rent.minimum_balance(8 + PoolState::INIT_SPACE)
+        + 2 * rent.minimum_balance(spl_token::state::Account::LEN)
+        + rent.minimum_balance(spl_token::state::Mint::LEN);
+    assert_eq!(
+        banks_client.get_balance(keeper.pubkey()).await.unwrap(),
+        keeper_funds - rent_paid + 2 * KEEPER_REWARD
+    );
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert_eq!(curve.graduation_platform_payout, 0);
+    assert_eq!(curve.graduation_keeper_reward, 2 * KEEPER_REWARD);
+    let pool = pool_state(&mut banks_client, keys.pool_state).await;
+    assert_eq!(pool.sol_reserves, (TREASURY_BALANCE - 2 * KEEPER_REWARD) * 90 / 100);
+}
+
+#[tokio::test]
+async fn test_pool_swaps_both_directions() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let user = Keypair::new();
+    let user_token_account = Pubkey::new_unique();
+
+    let mut program_test = pool_program_test(&keys, graduated);
+    program_test.add_account(
+        user.pubkey(),
+        Account::new(10 * LAMPORTS_PER_SOL, 0, &system_program::ID),
+    );
+    program_test.add_account(
+        user_token_account,
+        packed_account(
+            spl_token::state::Account {
+                mint: keys.mint,
+                owner: user.pubkey(),
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            },
+            spl_token::ID,
+        ),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[create_pool_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let opening = pool_state(&mut banks_client, keys.pool_state).await;
+
+    // Buy with 1 SOL
+    let mut transaction = Transaction::new_with_payer(
+        &[swap_instruction(&keys, &user.pubkey(), &user_token_account, LAMPORTS_PER_SOL, true)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let tokens_bought = token_balance(&mut banks_client, user_token_account).await;
+    let after_buy = pool_state(&mut banks_client, keys.pool_state).await;
+    assert!(tokens_bought > 0);
+    assert_eq!(after_buy.sol_reserves, opening.sol_reserves + LAMPORTS_PER_SOL);
+    assert_eq!(after_buy.token_reserves, opening.token_reserves - tokens_bought);
+
+    // Sell everything back
+    let mut transaction = Transaction::new_with_payer(
+        &[swap_instruction(&keys, &user.pubkey(), &user_token_account, tokens_bought, false)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user], banks_client.get_latest_blockhash().await.unwrap());
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let after_sell = pool_state(&mut banks_client, keys.pool_state).await;
+    assert_eq!(token_balance(&mut banks_client, user_token_account).await, 0);
+    assert_eq!(after_sell.token_reserves, opening.token_reserves);
+    // Swap fees stay in the pool
+    assert!(after_sell.sol_reserves > opening.sol_reserves);
+
+    let sol_vault = banks_client.get_account(keys.sol_vault).await.unwrap().unwrap();
+    assert_eq!(sol_vault.lamports, after_sell.sol_reserves);
+}
+
+#[tokio::test]
+async fn test_pool_sells_leave_the_vault_rent_exempt() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let user = Keypair::new();
+    let user_token_account = Pubkey::new_unique();
+    let dump = 1_000_000_000_000;
+
+    let mut program_test = pool_program_test(&keys, graduated);
+    program_test.add_account(
+        user.pubkey(),
+        Account::new(10 * LAMPORTS_PER_SOL, 0, &system_program::ID),
+    );
+    program_test.add_account(
+        user_token_account,
+        packed_account(
+            spl_token::state::Account {
+                mint: keys.mint,
+                owner: user.pubkey(),
+                amount: dump,
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            },
+            spl_token::ID,
+        ),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[create_pool_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // A buy, then a sell of millions of times the token reserve, which
+    // would otherwise pay out nearly every lamport in the vault
+    for (amount_in, sol_to_token) in [(LAMPORTS_PER_SOL, true), (dump, false)] {
+        let swap =
+            swap_instruction(&keys, &user.pubkey(), &user_token_account, amount_in, sol_to_token);
+        let mut transaction = Transaction::new_with_payer(&[swap], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &user], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    let pool = pool_state(&mut banks_client, keys.pool_state).await;
+    let sol_vault = banks_client.get_account(keys.sol_vault).await.unwrap().unwrap();
+    assert_eq!(sol_vault.lamports, pool.sol_reserves);
+    assert!(sol_vault.lamports >= Rent::default().minimum_balance(0));
+}
+
+// LP tokens the DEX stubs mint for a pool
+const STUB_LP_AMOUNT: u64 = 1_000_000;
+
+/// Accounts the DEX stubs use beyond the ones migrate_to_dex checks:
+/// (pool, LP mint authority, token program, LP mint authority seed)
+fn stub_layout(target: MigrationTarget) -> (usize, usize, usize, &'static [u8]) {
+    match target {
+        MigrationTarget::RaydiumAmmV4 => (4, 5, 0, b"amm authority"),
+        MigrationTarget::RaydiumCpmm => (3, 2, 14, b"vault_and_lp_mint_auth_seed"),
+        MigrationTarget::InHousePool => unreachable!(),
+    }
+}
+
+/// Stand-in for a DEX's pool-creation instruction: checks the payer signed,
+/// records the instruction data in the pool account and mints
+/// STUB_LP_AMOUNT LP tokens to the payer's LP account
+fn dex_stub(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let target = if *program_id == RAYDIUM_AMM_V4_PROGRAM_ID {
+        MigrationTarget::RaydiumAmmV4
+    } else {
+        MigrationTarget::RaydiumCpmm
+    };
+    let layout = target.dex_account_layout(&Pubkey::default()).unwrap();
+    let (pool, lp_authority, token_program, seed) = stub_layout(target);
+
+    if !accounts[layout.payer].is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    accounts[pool].try_borrow_mut_data()?[..data.len()].copy_from_slice(data);
+
+    let (_, bump) = Pubkey::find_program_address(&[seed], program_id);
+    let mint_lp = spl_token::instruction::mint_to(
+        &spl_token::ID,
+        accounts[layout.lp_mint].key,
+        accounts[layout.lp_account].key,
+        accounts[lp_authority].key,
+        &[],
+        STUB_LP_AMOUNT,
+    )?;
+    invoke_signed(
+        &mint_lp,
+        &[
+            accounts[layout.lp_mint].clone(),
+            accounts[layout.lp_account].clone(),
+            accounts[lp_authority].clone(),
+            accounts[token_program].clone(),
+        ],
+        &[&[seed, &[bump]]],
+    )
+}
+
+struct DexAccounts {
+    target: MigrationTarget,
+    migration_authority: Pubkey,
+    migration_token_account: Pubkey,
+    migration_wsol_account: Pubkey,
+    pool: Pubkey,
+    lp_mint: Pubkey,
+    lp_authority: Pubkey,
+    lp_account: Pubkey,
+    // Remaining accounts of migrate_to_dex, in the DEX's order
+    dex_accounts: Vec<AccountMeta>,
+}
+
+impl DexAccounts {
+    fn new(keys: &PoolAccounts, target: MigrationTarget) -> Self {
+        let program_id = target.program_id().unwrap();
+        let layout = target.dex_account_layout(&keys.mint).unwrap();
+        let (pool_index, authority_index, token_program_index, seed) = stub_layout(target);
+
+        let (migration_authority, _) = Pubkey::find_program_address(
+            &[b"migration_authority", keys.mint.as_ref()],
+            &degenie_token_creator::ID,
+        );
+        let migration_token_account = get_associated_token_address(&migration_authority, &keys.mint);
+        let migration_wsol_account =
+            get_associated_token_address(&migration_authority, &native_mint::ID);
+        let (lp_authority, _) = Pubkey::find_program_address(&[seed], &program_id);
+        let (pool, lp_mint, lp_account) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+
+        // Accounts only the real DEX would read are left as placeholders
+        let mut dex_accounts: Vec<AccountMeta> = (0..layout.account_count)
+            .map(|_| AccountMeta::new(Pubkey::new_unique(), false))
+            .collect();
+        for (index, key) in [
+            (layout.payer, migration_authority),
+            (layout.token_mint, keys.mint),
+            (layout.sol_mint, native_mint::ID),
+            (layout.token_account, migration_token_account),
+            (layout.sol_account, migration_wsol_account),
+            (layout.lp_mint, lp_mint),
+            (layout.lp_account, lp_account),
+            (pool_index, pool),
+            (authority_index, lp_authority),
+        ] {
+            dex_accounts[index] = AccountMeta::new(key, false);
+        }
+        dex_accounts[token_program_index] = AccountMeta::new_readonly(spl_token::ID, false);
+
+        Self {
+            target,
+            migration_authority,
+            migration_token_account,
+            migration_wsol_account,
+            pool,
+            lp_mint,
+            lp_authority,
+            lp_account,
+            dex_accounts,
+        }
+    }
+
+    /// Register the DEX stub and the accounts it expects to find
+    fn add_to(&self, program_test: &mut ProgramTest) {
+        let program_name = match self.target {
+            MigrationTarget::RaydiumAmmV4 => "raydium_amm_v4_stub",
+            _ => "raydium_cpmm_stub",
+        };
+        let program_id = self.target.program_id().unwrap();
+        program_test.add_program(program_name, program_id, processor!(dex_stub));
+
+        program_test.add_account(
+            self.pool,
+            Account {
+                lamports: Rent::default().minimum_balance(64),
+                data: vec![0; 64],
+                owner: program_id,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        program_test.add_account(
+            self.lp_mint,
+            packed_account(
+                spl_token::state::Mint {
+                    mint_authority: Some(self.lp_authority).into(),
+                    supply: 0,
+                    decimals: 9,
+                    is_initialized: true,
+                    freeze_authority: None.into(),
+                },
+                spl_token::ID,
+            ),
+        );
+        program_test.add_account(
+            self.lp_account,
+            packed_account(
+                spl_token::state::Account {
+                    mint: self.lp_mint,
+                    owner: self.migration_authority,
+                    state: spl_token::state::AccountState::Initialized,
+                    ..Default::default()
+                },
+                spl_token::ID,
+            ),
+        );
+        program_test.add_account(
+            native_mint::ID,
+            packed_account(
+                spl_token::state::Mint {
+                    mint_authority: None.into(),
+                    supply: 0,
+                    decimals: native_mint::DECIMALS,
+                    is_initialized: true,
+                    freeze_authority: None.into(),
+                },
+                spl_token::ID,
+            ),
+        );
+    }
+}
+
+fn migrate_instruction(
+    keys: &PoolAccounts,
+    dex: &DexAccounts,
+    dex_program: Pubkey,
+    keeper: &Pubkey,
+) -> Instruction {
+    let mut accounts = accounts::MigrateToDex {
+        bonding_curve: keys.bonding_curve,
+        mint: keys.mint,
+        treasury: keys.treasury,
+        migration_authority: dex.migration_authority,
+        migration_token_account: dex.migration_token_account,
+        native_mint: native_mint::ID,
+        migration_wsol_account: dex.migration_wsol_account,
+        config: keys.config,
+        creator: keys.creator,
+        platform_treasury: keys.platform_treasury,
+        dex_program,
+        keeper: *keeper,
+        token_program: spl_token::ID,
+        associated_token_program: anchor_spl::associated_token::ID,
+        system_program: system_program::ID,
+    }
+    .to_account_metas(None);
+    accounts.extend(dex.dex_accounts.iter().cloned());
+
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts,
+        data: instruction::MigrateToDex {}.data(),
+    }
+}
+
+fn set_migration_target_instruction(
+    keys: &PoolAccounts,
+    migration_target: MigrationTarget,
+) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::ConfigureLaunch {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            authority: keys.creator,
+        }
+        .to_account_metas(None),
+        data: instruction::SetMigrationTarget { migration_target }.data(),
+    }
+}
+
+async fn assert_migrates_through_stub(target: MigrationTarget) {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let dex = DexAccounts::new(&keys, target);
+    let mut program_test = pool_program_test(&keys, graduated_to(target));
+    dex.add_to(&mut program_test);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let keeper_balance = banks_client.get_balance(payer.pubkey()).await.unwrap();
+    let migrate = migrate_instruction(&keys, &dex, target.program_id().unwrap(), &payer.pubkey());
+    let mut transaction = Transaction::new_with_payer(&[migrate.clone()], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The keeper fronted the liquidity and got it back, plus the reward,
+    // less the fee and the staging accounts' rent
+    let staging_rent = 2 * Rent::default().minimum_balance(spl_token::state::Account::LEN);
+    assert_eq!(
+        banks_client.get_balance(payer.pubkey()).await.unwrap(),
+        keeper_balance + KEEPER_REWARD - staging_rent - 5000
+    );
+
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert!(curve.is_migrated);
+    // 6 decimals, so curve units are raw tokens
+    let token_deposit = curve.total_supply - TOTAL_SUPPLY;
+    // The keeper reward comes off the top; the rest is split
+    let split_balance = TREASURY_BALANCE - KEEPER_REWARD;
+    let liquidity = split_balance * 85 / 100;
+    let sol_deposit = liquidity - DEX_MIGRATION_BUDGET;
+    assert!(token_deposit > 0);
+
+    // The migration authority staged both sides of the pool
+    assert_eq!(token_balance(&mut banks_client, dex.migration_token_account).await, token_deposit);
+    assert_eq!(token_balance(&mut banks_client, dex.migration_wsol_account).await, sol_deposit);
+
+    // and the DEX received them in its own instruction format
+    let pool = banks_client.get_account(dex.pool).await.unwrap().unwrap();
+    let read_u64 = |offset: usize| u64::from_le_bytes(pool.data[offset..offset + 8].try_into().unwrap());
+    match target {
+        MigrationTarget::RaydiumAmmV4 => {
+            assert_eq!(pool.data[0], 1);
+            assert_eq!((read_u64(10), read_u64(18)), (sol_deposit, token_deposit));
+        }
+        MigrationTarget::RaydiumCpmm => {
+            assert_eq!(pool.data[..8], hash(b"global:initialize").to_bytes()[..8]);
+            let expected = if keys.mint < native_mint::ID {
+                (token_deposit, sol_deposit)
+            } else {
+                (sol_deposit, token_deposit)
+            };
+            assert_eq!((read_u64(8), read_u64(16)), expected);
+        }
+        MigrationTarget::InHousePool => unreachable!(),
+    }
+
+    // Every LP token the DEX minted was burned
+    assert_eq!(token_balance(&mut banks_client, dex.lp_account).await, 0);
+    let lp_mint = banks_client.get_account(dex.lp_mint).await.unwrap().unwrap();
+    assert_eq!(spl_token::state::Mint::unpack(&lp_mint.data).unwrap().supply, 0);
+
+    // The stub charged no fees, so the whole budget went to the platform
+    // along with its share
+    let platform_payout = split_balance / 10;
+    let creator_payout = split_balance - liquidity - platform_payout;
+    assert_eq!(curve.graduation_platform_payout, platform_payout);
+    assert_eq!(curve.graduation_creator_payout, creator_payout);
+    let platform = banks_client.get_account(keys.platform_treasury).await.unwrap().unwrap();
+    assert_eq!(platform.lamports, platform_payout + DEX_MIGRATION_BUDGET);
+    let creator = banks_client.get_account(keys.creator).await.unwrap().unwrap();
+    assert_eq!(creator.lamports, creator_payout);
+    assert!(banks_client.get_account(dex.migration_authority).await.unwrap().is_none());
+
+    let treasury = banks_client.get_account(keys.treasury).await.unwrap().unwrap();
+    assert_eq!(treasury.lamports, Rent::default().minimum_balance(8 + Treasury::INIT_SPACE));
+
+    // A second migration is rejected
+    let mut transaction = Transaction::new_with_payer(&[migrate], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], banks_client.get_latest_blockhash().await.unwrap());
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_migrates_to_raydium_amm_v4() {
+    assert_migrates_through_stub(MigrationTarget::RaydiumAmmV4).await;
+}
+
+#[tokio::test]
+async fn test_migrates_to_raydium_cpmm() {
+    assert_migrates_through_stub(MigrationTarget::RaydiumCpmm).await;
+}
+
+#[tokio::test]
+async fn test_dex_migration_rejects_mismatched_accounts() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let target = MigrationTarget::RaydiumCpmm;
+    let dex = DexAccounts::new(&keys, target);
+    let mut program_test = pool_program_test(&keys, graduated_to(target));
+    dex.add_to(&mut program_test);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let layout = target.dex_account_layout(&keys.mint).unwrap();
+    let mut foreign_payer = DexAccounts::new(&keys, target);
+    foreign_payer.dex_accounts[layout.payer] = AccountMeta::new(payer.pubkey(), true);
+    let mut short = DexAccounts::new(&keys, target);
+    short.dex_accounts.pop();
+
+    let rejected = [
+        // LP tokens would go to the keeper instead of being burned
+        migrate_instruction(&keys, &foreign_payer, RAYDIUM_CPMM_PROGRAM_ID, &payer.pubkey()),
+        migrate_instruction(&keys, &short, RAYDIUM_CPMM_PROGRAM_ID, &payer.pubkey()),
+        // The creator chose CP-Swap, not AMM v4 or the in-house pool
+        migrate_instruction(&keys, &dex, RAYDIUM_AMM_V4_PROGRAM_ID, &payer.pubkey()),
+        create_pool_instruction(&keys, &payer.pubkey()),
+    ];
+    for instruction in rejected {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer], recent_blockhash);
+        assert!(banks_client.process_transaction(transaction).await.is_err());
+    }
+
+    let mut transaction = Transaction::new_with_payer(
+        &[migrate_instruction(&keys, &dex, RAYDIUM_CPMM_PROGRAM_ID, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_in_house_curves_cannot_migrate_to_a_dex() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let dex = DexAccounts::new(&keys, MigrationTarget::RaydiumCpmm);
+    let mut program_test = pool_program_test(&keys, graduated);
+    dex.add_to(&mut program_test);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[migrate_instruction(&keys, &dex, RAYDIUM_CPMM_PROGRAM_ID, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_dex_curves_too_small_to_migrate_fall_back_to_the_pool() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let target = MigrationTarget::RaydiumCpmm;
+    let dex = DexAccounts::new(&keys, target);
+    // An 85% liquidity share of the budget itself can't pay for a DEX pool
+    let mut program_test = pool_program_test(&keys, |curve| {
+        graduated_to(target)(curve);
+        curve.treasury_balance = DEX_MIGRATION_BUDGET;
+    });
+    dex.add_to(&mut program_test);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[migrate_instruction(&keys, &dex, RAYDIUM_CPMM_PROGRAM_ID, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_custom_error(
+        banks_client.process_transaction(transaction).await.unwrap_err(),
+        TokenCreatorError::LiquidityBelowMigrationBudget,
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[create_pool_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let pool = pool_state(&mut banks_client, keys.pool_state).await;
+    assert_eq!(pool.sol_reserves, (DEX_MIGRATION_BUDGET - KEEPER_REWARD) * 85 / 100);
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert!(curve.is_migrated);
+    assert!(curve.migration_target == MigrationTarget::InHousePool);
+}
+
+#[tokio::test]
+async fn test_launch_settings_are_locked_once_trading_starts() {
+    let creator = Keypair::new();
+    let mut keys = PoolAccounts::new(Pubkey::new_unique());
+    keys.creator = creator.pubkey();
+    let (mut banks_client, payer, recent_blockhash) =
+        pool_program_test(&keys, |_| {}).start().await;
+
+    // The curve has already sold TOTAL_SUPPLY tokens
+    for instruction in [
+        set_migration_target_instruction(&keys, MigrationTarget::RaydiumCpmm),
+        set_graduation_deadline_instruction(&keys, Some(i64::MAX)),
+        set_presale_instruction(&keys, Some([1; 32]), i64::MAX),
+        set_opening_auction_instruction(&keys, 60),
+    ] {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &creator], recent_blockhash);
+        assert!(banks_client.process_transaction(transaction).await.is_err());
+    }
+
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert!(curve.migration_target == MigrationTarget::InHousePool);
+    assert_eq!(curve.graduation_deadline, None);
+    assert_eq!(curve.presale_merkle_root, None);
+    assert_eq!(curve.opening_auction_end, 0);
+}
+
+fn set_graduation_deadline_instruction(
+    keys: &PoolAccounts,
+    graduation_deadline: Option<i64>,
+) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::ConfigureLaunch {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            authority: keys.creator,
+        }
+        .to_account_metas(None),
+        data: instruction::SetGraduationDeadline { graduation_deadline }.data(),
+    }
+}
+
+fn claim_refund_instruction(
+    keys: &PoolAccounts,
+    holder: &Pubkey,
+    holder_token_account: &Pubkey,
+    token_amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::ClaimRefund {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            treasury: keys.treasury,
+            holder: *holder,
+            holder_token_account: *holder_token_account,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::ClaimRefund { token_amount }.data(),
+    }
+}
+
+fn buy_instruction(
+    keys: &PoolAccounts,
+    buyer: &Pubkey,
+    buyer_token_account: &Pubkey,
+    sol_amount: u64,
+) -> Instruction {
+    let (user_tracker, _) = Pubkey::find_program_address(
+        &[b"user_tracker", keys.mint.as_ref(), buyer.as_ref()],
+        &degenie_token_creator::ID,
+    );
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::BuyTokens {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            buyer: *buyer,
+            buyer_token_account: *buyer_token_account,
+            user_tracker,
+            treasury: keys.treasury,
+            config: keys.config,
+            referrer: None,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::BuyTokens {
+            sol_amount,
+            min_tokens_out: 1,
+            deadline: None,
+            presale_proof: None,
+        }
+        .data(),
+    }
+}
+
+/// Program test with a holder owning `holding` of the curve's tokens
+fn holder_program_test(
+    keys: &PoolAccounts,
+    holder: &Keypair,
+    holder_token_account: Pubkey,
+    holding: u64,
+    configure: impl FnOnce(&mut BondingCurve),
+) -> ProgramTest {
+    let mut program_test = pool_program_test(keys, configure);
+    program_test.add_account(
+        holder.pubkey(),
+        Account::new(LAMPORTS_PER_SOL, 0, &system_program::ID),
+    );
+    program_test.add_account(
+        holder_token_account,
+        packed_account(
+            spl_token::state::Account {
+                mint: keys.mint,
+                owner: holder.pubkey(),
+                amount: holding,
+                state: spl_token::state::AccountState::Initialized,
+                ..Default::default()
+            },
+            spl_token::ID,
+        ),
+    );
+    program_test
+}
+
+#[tokio::test]
+async fn test_missed_deadline_refunds_holders_pro_rata() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let holding = TOTAL_SUPPLY / 5;
+
+    // The deadline is long past and the curve never graduated
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, holding, |curve| {
+            curve.graduation_deadline = Some(0)
+        })
+        .start()
+        .await;
+
+    // Buying and graduating are closed even though the threshold is met
+    for (instruction, signers) in [
+        (
+            buy_instruction(&keys, &holder.pubkey(), &holder_token_account, LAMPORTS_PER_SOL / 10),
+            vec![&payer, &holder],
+        ),
+        (graduate_instruction(&keys, &payer.pubkey()), vec![&payer]),
+    ] {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&signers, recent_blockhash);
+        assert!(banks_client.process_transaction(transaction).await.is_err());
+    }
+
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_refund_instruction(&keys, &holder.pubkey(), &holder_token_account, holding)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // A fifth of the supply gets a fifth of the curve's SOL back; the
+    // creation fee stays in the treasury
+    let refund = (TREASURY_BALANCE - CREATION_FEE) / 5;
+    let holder_account = banks_client.get_account(holder.pubkey()).await.unwrap().unwrap();
+    assert_eq!(holder_account.lamports, LAMPORTS_PER_SOL + refund);
+    assert_eq!(token_balance(&mut banks_client, holder_token_account).await, 0);
+
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert_eq!(curve.treasury_balance, TREASURY_BALANCE - refund);
+    assert_eq!(curve.curve_sol_balance, TREASURY_BALANCE - CREATION_FEE - refund);
+    assert_eq!(curve.total_supply, TOTAL_SUPPLY - holding);
+    let treasury = banks_client.get_account(keys.treasury).await.unwrap().unwrap();
+    assert_eq!(
+        treasury.lamports,
+        Rent::default().minimum_balance(8 + Treasury::INIT_SPACE) + TREASURY_BALANCE - refund
+    );
+}
+
+#[tokio::test]
+async fn test_missed_deadline_releases_the_creation_fee_to_the_platform() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let holding = TOTAL_SUPPLY / 5;
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, holding, |curve| {
+            curve.graduation_deadline = Some(0)
+        })
+        .start()
+        .await;
+
+    // Refunds only pay out the curve's SOL, so the creation fee goes to the platform
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_platform_fees_instruction(&keys)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let platform = banks_client.get_account(keys.platform_treasury).await.unwrap().unwrap();
+    assert_eq!(platform.lamports, CREATION_FEE);
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert_eq!(curve.treasury_balance, TREASURY_BALANCE - CREATION_FEE);
+    assert_eq!(curve.curve_sol_balance, TREASURY_BALANCE - CREATION_FEE);
+
+    // Holders are still refunded in full
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_refund_instruction(&keys, &holder.pubkey(), &holder_token_account, holding)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    let holder_account = banks_client.get_account(holder.pubkey()).await.unwrap().unwrap();
+    assert_eq!(holder_account.lamports, LAMPORTS_PER_SOL + (TREASURY_BALANCE - CREATION_FEE) / 5);
+
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_platform_fees_instruction(&keys)],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash =
+        banks_client.get_new_latest_blockhash(&recent_blockhash).await.unwrap();
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_custom_error(
+        banks_client.process_transaction(transaction).await.unwrap_err(),
+        TokenCreatorError::NoFeesToClaim,
+    );
+}
+
+#[tokio::test]
+async fn test_refunds_wait_for_a_missed_deadline() {
+    for graduation_deadline in [None, Some(i64::MAX)] {
+        let keys = PoolAccounts::new(Pubkey::new_unique());
+        let holder = Keypair::new();
+        let holder_token_account = Pubkey::new_unique();
+        let (mut banks_client, payer, recent_blockhash) = holder_program_test(
+            &keys,
+            &holder,
+            holder_token_account,
+            TOTAL_SUPPLY / 5,
+            |curve| curve.graduation_deadline = graduation_deadline,
+        )
+        .start()
+        .await;
+
+        let mut transaction = Transaction::new_with_payer(
+            &[claim_refund_instruction(&keys, &holder.pubkey(), &holder_token_account, 1)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &holder], recent_blockhash);
+        assert!(banks_client.process_transaction(transaction).await.is_err());
+    }
+}
+
+fn update_config_instruction(
+    keys: &PoolAccounts,
+    admin: &Pubkey,
+    params: ConfigParams,
+    new_admin: Option<Pubkey>,
+) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::UpdateConfig {
+            config: keys.config,
+            admin: *admin,
+        }
+        .to_account_metas(None),
+        data: instruction::UpdateConfig { params, new_admin }.data(),
+    }
+}
+
+#[tokio::test]
+async fn test_config_admin_redirects_platform_payouts() {
+    let admin = Keypair::new();
+    let mut keys = PoolAccounts::new(Pubkey::new_unique());
+    keys.admin = admin.pubkey();
+    let (mut banks_client, payer, recent_blockhash) =
+        pool_program_test(&keys, graduated).start().await;
+
+    let new_platform_treasury = Pubkey::new_unique();
+    let mut out_of_range = config_params(new_platform_treasury);
+    out_of_range.graduation_creator_bps = 400;
+    let rejected = [
+        // Only the admin may change the config
+        (
+            update_config_instruction(
+                &keys,
+                &payer.pubkey(),
+                config_params(new_platform_treasury),
+                None,
+            ),
+            vec![&payer],
+        ),
+        (
+            update_config_instruction(&keys, &admin.pubkey(), out_of_range, None),
+            vec![&payer, &admin],
+        ),
+    ];
+    for (instruction, signers) in rejected {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&signers, recent_blockhash);
+        assert!(banks_client.process_transaction(transaction).await.is_err());
+    }
+
+    let mut transaction = Transaction::new_with_payer(
+        &[update_config_instruction(
+            &keys,
+            &admin.pubkey(),
+            config_params(new_platform_treasury),
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The old platform wallet is no longer accepted
+    let mut transaction = Transaction::new_with_payer(
+        &[create_pool_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    keys.platform_treasury = new_platform_treasury;
+    let mut transaction = Transaction::new_with_payer(
+        &[create_pool_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let platform = banks_client.get_account(new_platform_treasury).await.unwrap().unwrap();
+    assert_eq!(platform.lamports, (TREASURY_BALANCE - KEEPER_REWARD) / 10);
+}
+
+#[derive(Clone, Copy)]
+enum PauseScope {
+    Protocol,
+    Curve,
+}
+
+fn pause_instruction(keys: &PoolAccounts, scope: PauseScope, paused: bool) -> Instruction {
+    match scope {
+        PauseScope::Protocol => Instruction {
+            program_id: degenie_token_creator::ID,
+            accounts: accounts::UpdateConfig {
+                config: keys.config,
+                admin: keys.admin,
+            }
+            .to_account_metas(None),
+            data: instruction::SetProtocolPaused { paused }.data(),
+        },
+        PauseScope::Curve => Instruction {
+            program_id: degenie_token_creator::ID,
+            accounts: accounts::PauseCurve {
+                config: keys.config,
+                bonding_curve: keys.bonding_curve,
+                mint: keys.mint,
+                admin: keys.admin,
+            }
+            .to_account_metas(None),
+            data: instruction::SetCurvePaused { paused }.data(),
+        },
+    }
+}
+
+fn set_presale_instruction(
+    keys: &PoolAccounts,
+    merkle_root: Option<[u8; 32]>,
+    presale_end: i64,
+) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::ConfigureLaunch {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            authority: keys.creator,
+        }
+        .to_account_metas(None),
+        data: instruction::SetPresale { merkle_root, presale_end }.data(),
+    }
+}
+
+fn set_opening_auction_instruction(keys: &PoolAccounts, duration: i64) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::ConfigureLaunch {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            authority: keys.creator,
+        }
+        .to_account_metas(None),
+        data: instruction::SetOpeningAuction { duration }.data(),
+    }
+}
+
+fn presale_buy_instruction(
+    keys: &PoolAccounts,
+    buyer: &Pubkey,
+    buyer_token_account: &Pubkey,
+    sol_amount: u64,
+    presale_proof: PresaleProof,
+) -> Instruction {
+    let mut buy = buy_instruction(keys, buyer, buyer_token_account, sol_amount);
+    buy.data = instruction::BuyTokens {
+        sol_amount,
+        min_tokens_out: 1,
+        deadline: None,
+        presale_proof: Some(presale_proof),
+    }
+    .data();
+    buy
+}
+
+fn buy_exact_instruction(
+    keys: &PoolAccounts,
+    buyer: &Pubkey,
+    buyer_token_account: &Pubkey,
+    token_amount: u64,
+) -> Instruction {
+    let mut buy = buy_instruction(keys, buyer, buyer_token_account, 0);
+    buy.data = instruction::BuyExactTokens {
+        token_amount,
+        max_sol_in: u64::MAX,
+        deadline: None,
+        presale_proof: None,
+    }
+    .data();
+    buy
+}
+
+fn sell_accounts(
+    keys: &PoolAccounts,
+    seller: &Pubkey,
+    seller_token_account: &Pubkey,
+) -> Vec<AccountMeta> {
+    accounts::SellTokens {
+        bonding_curve: keys.bonding_curve,
+        mint: keys.mint,
+        seller: *seller,
+        seller_token_account: *seller_token_account,
+        treasury: keys.treasury,
+        config: keys.config,
+        referrer: None,
+        token_program: spl_token::ID,
+        system_program: system_program::ID,
+    }
+    .to_account_metas(None)
+}
+
+fn sell_instruction(
+    keys: &PoolAccounts,
+    seller: &Pubkey,
+    seller_token_account: &Pubkey,
+    token_amount: u64,
+) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: sell_accounts(keys, seller, seller_token_account),
+        data: instruction::SellTokens {
+            token_amount,
+            min_sol_out: 1,
+            deadline: None,
+        }
+        .data(),
+    }
+}
+
+fn sell_for_exact_sol_instruction(
+    keys: &PoolAccounts,
+    seller: &Pubkey,
+    seller_token_account: &Pubkey,
+    sol_out: u64,
+) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: sell_accounts(keys, seller, seller_token_account),
+        data: instruction::SellForExactSol {
+            sol_out,
+            max_tokens_in: u64::MAX,
+            deadline: None,
+        }
+        .data(),
+    }
+}
+
+/// Assert the transaction's first instruction failed with `expected`
+fn assert_custom_error(error: BanksClientError, expected: TokenCreatorError) {
+    assert_eq!(
+        error.unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(expected.into()))
+    );
+}
+
+fn assert_trading_paused(error: BanksClientError) {
+    assert_custom_error(error, TokenCreatorError::TradingPaused);
+}
+
+#[tokio::test]
+async fn test_pause_blocks_curve_trading_and_graduation() {
+    for scope in [PauseScope::Protocol, PauseScope::Curve] {
+        let admin = Keypair::new();
+        let holder = Keypair::new();
+        let holder_token_account = Pubkey::new_unique();
+        let mut keys = PoolAccounts::new(Pubkey::new_unique());
+        keys.admin = admin.pubkey();
+        let (mut banks_client, payer, recent_blockhash) =
+            holder_program_test(&keys, &holder, holder_token_account, TOTAL_SUPPLY / 5, |_| {})
+                .start()
+                .await;
+
+        let mut transaction = Transaction::new_with_payer(
+            &[pause_instruction(&keys, scope, true)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &admin], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        let holder_key = holder.pubkey();
+        let gated = [
+            buy_instruction(&keys, &holder_key, &holder_token_account, LAMPORTS_PER_SOL / 10),
+            buy_exact_instruction(&keys, &holder_key, &holder_token_account, 1_000),
+            sell_instruction(&keys, &holder_key, &holder_token_account, 1_000),
+            sell_for_exact_sol_instruction(&keys, &holder_key, &holder_token_account, 1_000_000),
+            graduate_instruction(&keys, &holder_key),
+        ];
+        for instruction in gated {
+            let mut transaction =
+                Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+            transaction.sign(&[&payer, &holder], recent_blockhash);
+            assert_trading_paused(banks_client.process_transaction(transaction).await.unwrap_err());
+        }
+
+        let mut transaction = Transaction::new_with_payer(
+            &[pause_instruction(&keys, scope, false)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &admin], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        // Trading and graduation resume once the pause is lifted
+        for instruction in [
+            sell_instruction(&keys, &holder_key, &holder_token_account, 2_000),
+            graduate_instruction(&keys, &holder_key),
+        ] {
+            let mut transaction =
+                Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+            transaction.sign(
+                &[&payer, &holder],
+                banks_client.get_new_latest_blockhash(&recent_blockhash).await.unwrap(),
+            );
+            banks_client.process_transaction(transaction).await.unwrap();
+        }
+        assert!(bonding_curve(&mut banks_client, keys.bonding_curve).await.is_graduated);
+
+        // The opening auction halts too, but fills already settled can still
+        // be claimed, like refunds
+        let mut keys = PoolAccounts::new(Pubkey::new_unique());
+        keys.admin = admin.pubkey();
+        let commits = [(holder_key, LAMPORTS_PER_SOL / 10)];
+        let (mut banks_client, payer, recent_blockhash) =
+            ended_auction_program_test(&keys, &holder, holder_token_account, &commits, |curve| {
+                curve.auction_settled = true;
+                curve.auction_filled_sol = LAMPORTS_PER_SOL / 10;
+                curve.auction_token_amount = 1_000;
+            })
+            .start()
+            .await;
+
+        let mut transaction = Transaction::new_with_payer(
+            &[pause_instruction(&keys, scope, true)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &admin], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        for instruction in [
+            commit_buy_instruction(&keys, &holder_key, LAMPORTS_PER_SOL / 10),
+            settle_opening_auction_instruction(&keys, &holder_key),
+        ] {
+            let mut transaction =
+                Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+            transaction.sign(&[&payer, &holder], recent_blockhash);
+            assert_trading_paused(banks_client.process_transaction(transaction).await.unwrap_err());
+        }
+
+        let mut transaction = Transaction::new_with_payer(
+            &[claim_auction_fill_instruction(&keys, &holder_key, &holder_token_account)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+        assert_eq!(token_balance(&mut banks_client, holder_token_account).await, 1_000);
+    }
+}
+
+#[tokio::test]
+async fn test_pause_blocks_pool_creation_migration_and_swaps() {
+    for scope in [PauseScope::Protocol, PauseScope::Curve] {
+        let admin = Keypair::new();
+        let user = Keypair::new();
+        let user_token_account = Pubkey::new_unique();
+        let mut keys = PoolAccounts::new(Pubkey::new_unique());
+        keys.admin = admin.pubkey();
+        let dex = DexAccounts::new(&keys, MigrationTarget::RaydiumCpmm);
+
+        let mut program_test = pool_program_test(&keys, graduated);
+        dex.add_to(&mut program_test);
+        program_test.add_account(
+            user.pubkey(),
+            Account::new(10 * LAMPORTS_PER_SOL, 0, &system_program::ID),
+        );
+        program_test.add_account(
+            user_token_account,
+            packed_account(
+                spl_token::state::Account {
+                    mint: keys.mint,
+                    owner: user.pubkey(),
+                    state: spl_token::state::AccountState::Initialized,
+                    ..Default::default()
+                },
+                spl_token::ID,
+            ),
+        );
+        let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+        let mut transaction = Transaction::new_with_payer(
+            &[pause_instruction(&keys, scope, true)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &admin], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        // The pause is checked before the migration target
+        for instruction in [
+            create_pool_instruction(&keys, &payer.pubkey()),
+            migrate_instruction(&keys, &dex, RAYDIUM_CPMM_PROGRAM_ID, &payer.pubkey()),
+        ] {
+            let mut transaction =
+                Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+            transaction.sign(&[&payer], recent_blockhash);
+            assert_trading_paused(banks_client.process_transaction(transaction).await.unwrap_err());
+        }
+
+        let mut transaction = Transaction::new_with_payer(
+            &[
+                pause_instruction(&keys, scope, false),
+                create_pool_instruction(&keys, &payer.pubkey()),
+            ],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &admin], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+        assert!(bonding_curve(&mut banks_client, keys.bonding_curve).await.is_migrated);
+
+        let recent_blockhash =
+            banks_client.get_new_latest_blockhash(&recent_blockhash).await.unwrap();
+        let mut transaction = Transaction::new_with_payer(
+            &[pause_instruction(&keys, scope, true)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &admin], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        let mut transaction = Transaction::new_with_payer(
+            &[swap_instruction(&keys, &user.pubkey(), &user_token_account, LAMPORTS_PER_SOL, true)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &user], recent_blockhash);
+        assert_trading_paused(banks_client.process_transaction(transaction).await.unwrap_err());
+    }
+}
+
+#[tokio::test]
+async fn test_refunds_stay_open_while_paused() {
+    let admin = Keypair::new();
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let mut keys = PoolAccounts::new(Pubkey::new_unique());
+    keys.admin = admin.pubkey();
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, TOTAL_SUPPLY / 5, |curve| {
+            curve.graduation_deadline = Some(0)
+        })
+        .start()
+        .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            pause_instruction(&keys, PauseScope::Protocol, true),
+            pause_instruction(&keys, PauseScope::Curve, true),
+            claim_refund_instruction(&keys, &holder.pubkey(), &holder_token_account, 1_000),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &admin, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        token_balance(&mut banks_client, holder_token_account).await,
+        TOTAL_SUPPLY / 5 - 1_000
+    );
+}
+
+#[tokio::test]
+async fn test_only_the_admin_can_pause() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let (mut banks_client, payer, recent_blockhash) =
+        pool_program_test(&keys, |_| {}).start().await;
+
+    let mut impostor = PoolAccounts::new(keys.mint);
+    impostor.admin = payer.pubkey();
+    for scope in [PauseScope::Protocol, PauseScope::Curve] {
+        let mut transaction = Transaction::new_with_payer(
+            &[pause_instruction(&impostor, scope, true)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        assert!(banks_client.process_transaction(transaction).await.is_err());
+    }
+
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert!(!curve.paused);
+}
+
+/// buy_tokens, buy_exact_tokens, sell_tokens and sell_for_exact_sol with
+/// slippage bounds at exactly what `curve` would fill, tightened by
+/// `past_quote` units beyond that
+fn quoted_trades(
+    keys: &PoolAccounts,
+    trader: &Pubkey,
+    trader_token_account: &Pubkey,
+    curve: &BondingCurve,
+    past_quote: u64,
+    deadline: Option<i64>,
+) -> [Instruction; 4] {
+    // The fixture curve launched long ago, so no sniper tax applies
+    let now = curve.creation_timestamp + curve.launch_protection_period;
+    let (sol_amount, token_amount, sol_out) = (LAMPORTS_PER_SOL / 10, 1_000, 1_000_000);
+
+    let min_tokens_out = calculate_buy_quote(sol_amount, curve, now).unwrap().token_amount;
+    let curve_cost = calculate_sol_for_exact_tokens(token_amount, curve).unwrap();
+    let max_sol_in = gross_amount_for_net(curve_cost, curve.transaction_fee_bps).unwrap();
+    let min_sol_out = calculate_sell_quote(token_amount, curve).unwrap().sol_amount;
+    let (max_tokens_in, _) = calculate_tokens_for_exact_sol(sol_out, curve).unwrap();
+
+    let with_data = |mut trade: Instruction, data: Vec<u8>| {
+        trade.data = data;
+        trade
+    };
+    [
+        with_data(
+            buy_instruction(keys, trader, trader_token_account, sol_amount),
+            instruction::BuyTokens {
+                sol_amount,
+                min_tokens_out: min_tokens_out + past_quote,
+                deadline,
+                presale_proof: None,
+            }
+            .data(),
+        ),
+        with_data(
+            buy_instruction(keys, trader, trader_token_account, 0),
+            instruction::BuyExactTokens {
+                token_amount,
+                max_sol_in: max_sol_in - past_quote,
+                deadline,
+                presale_proof: None,
+            }
+            .data(),
+        ),
+        with_data(
+            sell_instruction(keys, trader, trader_token_account, token_amount),
+            instruction::SellTokens {
+                token_amount,
+                min_sol_out: min_sol_out + past_quote,
+                deadline,
+            }
+            .data(),
+        ),
+        with_data(
+            sell_instruction(keys, trader, trader_token_account, 0),
+            instruction::SellForExactSol {
+                sol_out,
+                max_tokens_in: max_tokens_in - past_quote,
+                deadline,
+            }
+            .data(),
+        ),
+    ]
+}
+
+#[tokio::test]
+async fn test_trades_enforce_slippage_bounds_and_deadlines() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, TOTAL_SUPPLY / 5, |curve| {
+            curve.graduation_threshold = u64::MAX;
+            curve.transaction_cooldown = 0;
+        })
+        .start()
+        .await;
+
+    let holder_key = holder.pubkey();
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    let rejected = [
+        // One unit past the quote in the user's favour
+        (
+            quoted_trades(&keys, &holder_key, &holder_token_account, &curve, 1, None),
+            TokenCreatorError::SlippageExceeded,
+        ),
+        // Within the bounds but landing after the deadline
+        (
+            quoted_trades(&keys, &holder_key, &holder_token_account, &curve, 0, Some(0)),
+            TokenCreatorError::TransactionExpired,
+        ),
+    ];
+    for (trades, error) in rejected {
+        for trade in trades {
+            let mut transaction = Transaction::new_with_payer(&[trade], Some(&payer.pubkey()));
+            transaction.sign(&[&payer, &holder], recent_blockhash);
+            let result = banks_client.process_transaction(transaction).await;
+            assert_custom_error(result.unwrap_err(), error);
+        }
+    }
+
+    // Bounds at exactly the quote go through, each quoted against the curve it lands on
+    for index in 0..4 {
+        let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+        let trades =
+            quoted_trades(&keys, &holder_key, &holder_token_account, &curve, 0, Some(i64::MAX));
+        let trade = trades[index].clone();
+        let mut transaction = Transaction::new_with_payer(&[trade], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &holder], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_exact_sol_sell_rounding_stays_within_the_balance() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    // At 5 decimals one raw token is 10 curve units; the holder has 3000 units
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, 300, |curve| {
+            curve.decimals = 5;
+        })
+        .start()
+        .await;
+
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    let net_for = |token_amount| {
+        let gross = calculate_sol_for_tokens_with_curve(token_amount, &curve).unwrap();
+        gross - calculate_transaction_fee(gross, &curve).unwrap()
+    };
+
+    // 3005 units round up to a 301st raw token the holder doesn't have
+    let mut transaction = Transaction::new_with_payer(
+        &[sell_for_exact_sol_instruction(
+            &keys,
+            &holder.pubkey(),
+            &holder_token_account,
+            net_for(3005),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    assert_custom_error(
+        banks_client.process_transaction(transaction).await.unwrap_err(),
+        TokenCreatorError::InsufficientBalance,
+    );
+
+    // The whole balance is still sellable
+    let mut transaction = Transaction::new_with_payer(
+        &[sell_for_exact_sol_instruction(
+            &keys,
+            &holder.pubkey(),
+            &holder_token_account,
+            net_for(3000),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert_eq!(token_balance(&mut banks_client, holder_token_account).await, 0);
+}
+
+fn quote_instruction(keys: &PoolAccounts, data: Vec<u8>) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::QuoteTrade {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+        }
+        .to_account_metas(None),
+        data,
+    }
+}
+
+/// Run a quote instruction and decode the TradeQuote from its return data
+async fn quote(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: Hash,
+    instruction: Instruction,
+) -> TradeQuote {
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[payer], recent_blockhash);
+    let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
+    result.result.unwrap();
+
+    let return_data = result.metadata.unwrap().return_data.unwrap();
+    assert_eq!(return_data.program_id, degenie_token_creator::ID);
+    TradeQuote::try_from_slice(&return_data.data).unwrap()
+}
+
+#[tokio::test]
+async fn test_quotes_match_the_trades_that_follow() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let holding = TOTAL_SUPPLY / 5;
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, holding, |curve| {
+            curve.graduation_threshold = u64::MAX;
+            curve.transaction_cooldown = 0;
+        })
+        .start()
+        .await;
+
+    let sol_amount = LAMPORTS_PER_SOL / 10;
+    let buy_quote = quote(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        quote_instruction(&keys, instruction::QuoteBuy { sol_amount }.data()),
+    )
+    .await;
+    let before = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    let mut transaction = Transaction::new_with_payer(
+        &[buy_instruction(&keys, &holder.pubkey(), &holder_token_account, sol_amount)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    // Processed in-line, so the sell quote below can't race the buy's account locks
+    let result = banks_client.process_transaction_with_metadata(transaction).await.unwrap();
+    result.result.unwrap();
+
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    let bought = token_balance(&mut banks_client, holder_token_account).await - holding;
+    assert_eq!(buy_quote.token_amount, bought);
+    assert_eq!(buy_quote.sol_amount, sol_amount);
+    assert_eq!(buy_quote.transaction_fee, buy_quote.creator_fee + buy_quote.platform_fee);
+    assert_eq!(buy_quote.creator_fee, curve.creator_fees_owed - before.creator_fees_owed);
+    assert_eq!(buy_quote.platform_fee, curve.platform_fees_owed - before.platform_fees_owed);
+    assert_eq!(
+        sol_amount - buy_quote.transaction_fee - buy_quote.sniper_tax,
+        curve.curve_sol_balance - before.curve_sol_balance
+    );
+    assert_eq!(buy_quote.new_price, curve.current_price);
+
+    let token_amount = 2_000;
+    let sell_quote = quote(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        quote_instruction(&keys, instruction::QuoteSell { token_amount }.data()),
+    )
+    .await;
+    let lamports_before = banks_client.get_balance(holder.pubkey()).await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[sell_instruction(&keys, &holder.pubkey(), &holder_token_account, token_amount)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let received = banks_client.get_balance(holder.pubkey()).await.unwrap() - lamports_before;
+    assert_eq!(sell_quote.sol_amount, received);
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert_eq!(sell_quote.new_price, curve.current_price);
+    assert_eq!(
+        token_balance(&mut banks_client, holder_token_account).await,
+        holding + bought - token_amount
+    );
+}
+
+#[tokio::test]
+async fn test_large_sells_exceed_the_price_impact_limit() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let holding = TOTAL_SUPPLY / 5;
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, holding, |_| {})
+            .start()
+            .await;
+
+    // Dumping a fifth of the supply drops the price far past 5%, by tokens
+    // in or by SOL out
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    let dump_proceeds = calculate_sell_quote(holding, &curve).unwrap().sol_amount;
+    let holder_key = holder.pubkey();
+    for instruction in [
+        sell_instruction(&keys, &holder_key, &holder_token_account, holding),
+        sell_for_exact_sol_instruction(&keys, &holder_key, &holder_token_account, dump_proceeds),
+    ] {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &holder], recent_blockhash);
+        assert_custom_error(
+            banks_client.process_transaction(transaction).await.unwrap_err(),
+            TokenCreatorError::ExceedsPriceImpactLimit,
+        );
+    }
+    assert_eq!(token_balance(&mut banks_client, holder_token_account).await, holding);
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert_eq!(curve.total_supply, TOTAL_SUPPLY);
+}
+
+#[tokio::test]
+async fn test_graduating_buy_books_the_trade_and_graduates() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, 0, |_| {}).start().await;
+
+    // The curve is already past its threshold, so the first buy graduates it
+    let before = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert!(!before.is_graduated);
+    let sol_amount = LAMPORTS_PER_SOL / 2;
+    let mut transaction = Transaction::new_with_payer(
+        &[buy_instruction(&keys, &holder.pubkey(), &holder_token_account, sol_amount)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    let transaction_fee = sol_amount / 100;
+    assert_eq!(curve.total_volume - before.total_volume, sol_amount);
+    assert_eq!(
+        curve.creator_fees_owed - before.creator_fees_owed + curve.platform_fees_owed
+            - before.platform_fees_owed,
+        transaction_fee
+    );
+    // The sniper tax goes to the treasury too, so only the fee and the
+    // graduation reward stay out of it
+    assert_eq!(
+        curve.treasury_balance - before.treasury_balance,
+        sol_amount - transaction_fee - KEEPER_REWARD
+    );
+    assert!(token_balance(&mut banks_client, holder_token_account).await > 0);
+    assert!(curve.total_supply > before.total_supply);
+    assert!(curve.current_price > before.current_price);
+    assert!(curve.is_graduated);
+    assert!(!curve.is_migrated);
+
+    // The buyer graduated the curve, so the buyer earned the keeper reward
+    let tracker_rent = Rent::default().minimum_balance(8 + UserTracker::INIT_SPACE);
+    assert_eq!(
+        banks_client.get_balance(holder.pubkey()).await.unwrap(),
+        LAMPORTS_PER_SOL - sol_amount - tracker_rent + KEEPER_REWARD
+    );
+    assert_eq!(curve.graduation_keeper_reward, KEEPER_REWARD);
+}
+
+fn claim_creator_fees_instruction(keys: &PoolAccounts, authority: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::ClaimCreatorFees {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            treasury: keys.treasury,
+            config: keys.config,
+            authority: *authority,
+        }
+        .to_account_metas(None),
+        data: instruction::ClaimCreatorFees {}.data(),
+    }
+}
+
+fn claim_platform_fees_instruction(keys: &PoolAccounts) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::ClaimPlatformFees {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            treasury: keys.treasury,
+            config: keys.config,
+            platform_treasury: keys.platform_treasury,
+        }
+        .to_account_metas(None),
+        data: instruction::ClaimPlatformFees {}.data(),
+    }
+}
+
+#[tokio::test]
+async fn test_trading_fees_accrue_until_claimed() {
+    let creator = Keypair::new();
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let mut keys = PoolAccounts::new(Pubkey::new_unique());
+    keys.creator = creator.pubkey();
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, TOTAL_SUPPLY / 5, |_| {})
+            .start()
+            .await;
+
+    // Neither trade touches the creator or platform wallets, which don't exist yet
+    let holder_key = holder.pubkey();
+    for instruction in [
+        sell_instruction(&keys, &holder_key, &holder_token_account, 2_000),
+        buy_instruction(&keys, &holder_key, &holder_token_account, LAMPORTS_PER_SOL / 2),
+    ] {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &holder], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+    assert!(banks_client.get_account(keys.creator).await.unwrap().is_none());
+    assert!(banks_client.get_account(keys.platform_treasury).await.unwrap().is_none());
+
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    let (creator_owed, platform_owed) = (curve.creator_fees_owed, curve.platform_fees_owed);
+    assert!(creator_owed > 0 && platform_owed > 0);
+    let treasury = banks_client.get_account(keys.treasury).await.unwrap().unwrap();
+    assert_eq!(
+        treasury.lamports,
+        Rent::default().minimum_balance(8 + Treasury::INIT_SPACE)
+            + curve.treasury_balance
+            + creator_owed
+            + platform_owed
+    );
+
+    // Only the creator can claim the creator's share
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_creator_fees_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            claim_creator_fees_instruction(&keys, &creator.pubkey()),
+            claim_platform_fees_instruction(&keys),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &creator], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let creator_account = banks_client.get_account(keys.creator).await.unwrap().unwrap();
+    assert_eq!(creator_account.lamports, creator_owed);
+    let platform = banks_client.get_account(keys.platform_treasury).await.unwrap().unwrap();
+    assert_eq!(platform.lamports, platform_owed);
+    let after = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert_eq!((after.creator_fees_owed, after.platform_fees_owed), (0, 0));
+    assert_eq!(after.treasury_balance, curve.treasury_balance);
+
+    // Nothing left to claim
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_platform_fees_instruction(&keys)],
+        Some(&payer.pubkey()),
+    );
+    let recent_blockhash =
+        banks_client.get_new_latest_blockhash(&recent_blockhash).await.unwrap();
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(TokenCreatorError::NoFeesToClaim.into())
+        )
+    );
+}
+
+fn referrer_address(wallet: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"referrer", wallet.as_ref()], &degenie_token_creator::ID).0
+}
+
+/// Name `wallet` as the referrer of a buy or sell built without one
+fn referred(mut trade: Instruction, wallet: &Pubkey) -> Instruction {
+    // Anchor passes the program id in place of a missing optional account
+    let placeholder = trade
+        .accounts
+        .iter_mut()
+        .find(|meta| meta.pubkey == degenie_token_creator::ID)
+        .unwrap();
+    *placeholder = AccountMeta::new(referrer_address(wallet), false);
+    trade
+}
+
+fn register_referrer_instruction(wallet: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::RegisterReferrer {
+            referrer: referrer_address(wallet),
+            wallet: *wallet,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::RegisterReferrer {}.data(),
+    }
+}
+
+fn claim_referral_fees_instruction(keys: &PoolAccounts, wallet: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::ClaimReferralFees {
+            referrer: referrer_address(wallet),
+            config: keys.config,
+            wallet: *wallet,
+        }
+        .to_account_metas(None),
+        data: instruction::ClaimReferralFees {}.data(),
+    }
+}
+
+#[tokio::test]
+async fn test_referrers_earn_part_of_the_platform_fee() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let affiliate = Keypair::new();
+    let mut program_test =
+        holder_program_test(&keys, &holder, holder_token_account, TOTAL_SUPPLY / 5, |_| {});
+    program_test.add_account(
+        affiliate.pubkey(),
+        Account::new(LAMPORTS_PER_SOL, 0, &system_program::ID),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            register_referrer_instruction(&affiliate.pubkey()),
+            register_referrer_instruction(&holder.pubkey()),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &affiliate, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Traders can't send their own fees back to themselves
+    let holder_key = holder.pubkey();
+    let sell = sell_instruction(&keys, &holder_key, &holder_token_account, 2_000);
+    let mut transaction = Transaction::new_with_payer(
+        &[referred(sell.clone(), &holder_key)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(TokenCreatorError::SelfReferral.into())
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[referred(sell, &affiliate.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    let referrer_account = banks_client
+        .get_account(referrer_address(&affiliate.pubkey()))
+        .await
+        .unwrap()
+        .unwrap();
+    let referrer = Referrer::try_deserialize(&mut referrer_account.data.as_slice()).unwrap();
+    let transaction_fee =
+        curve.creator_fees_owed + curve.platform_fees_owed + referrer.fees_owed;
+    assert!(referrer.fees_owed > 0);
+    assert_eq!(referrer.fees_owed, calculate_referral_fee(transaction_fee, &curve).unwrap());
+    assert_eq!(referrer.total_earned, referrer.fees_owed);
+    // The creator's half is untouched
+    assert_eq!(curve.creator_fees_owed, transaction_fee / 2);
+    assert_eq!(
+        referrer_account.lamports,
+        Rent::default().minimum_balance(8 + Referrer::INIT_SPACE) + referrer.fees_owed
+    );
+
+    let wallet_before = banks_client.get_balance(affiliate.pubkey()).await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_referral_fees_instruction(&keys, &affiliate.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &affiliate], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let wallet_after = banks_client.get_balance(affiliate.pubkey()).await.unwrap();
+    assert_eq!(wallet_after, wallet_before + referrer.fees_owed);
+}
+
+/// A creation time ahead of the bank clock, so buys land at the very start
+/// of the launch window
+fn upcoming_launch() -> i64 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap();
+    now.as_secs() as i64 + 86_400
+}
+
+#[tokio::test]
+async fn test_launch_buys_pay_the_sniper_tax() {
+    for recipient in [SniperTaxRecipient::Treasury, SniperTaxRecipient::Creator] {
+        let keys = PoolAccounts::new(Pubkey::new_unique());
+        let holder = Keypair::new();
+        let holder_token_account = Pubkey::new_unique();
+        // 50% in total at launch, 49% on top of the 1% base fee
+        let (mut banks_client, payer, recent_blockhash) =
+            holder_program_test(&keys, &holder, holder_token_account, 0, |curve| {
+                curve.creation_timestamp = upcoming_launch();
+                curve.sniper_tax_recipient = recipient;
+                curve.graduation_threshold = u64::MAX;
+            })
+            .start()
+            .await;
+
+        let sol_amount = LAMPORTS_PER_SOL / 2;
+        let mut transaction = Transaction::new_with_payer(
+            &[buy_instruction(&keys, &holder.pubkey(), &holder_token_account, sol_amount)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &holder], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+
+        let transaction_fee = sol_amount / 100;
+        let sniper_tax = sol_amount * 4900 / 10000;
+        let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+        assert_eq!(curve.platform_fees_owed, transaction_fee / 2);
+        let curve_sol = sol_amount - transaction_fee - sniper_tax;
+        match recipient {
+            SniperTaxRecipient::Treasury => {
+                assert_eq!(curve.treasury_balance, TREASURY_BALANCE + curve_sol + sniper_tax);
+                assert_eq!(curve.creator_fees_owed, transaction_fee / 2);
+            }
+            SniperTaxRecipient::Creator => {
+                assert_eq!(curve.treasury_balance, TREASURY_BALANCE + curve_sol);
+                assert_eq!(curve.creator_fees_owed, transaction_fee / 2 + sniper_tax);
+            }
+        }
+        // Kept in the treasury the tax backs refunds; paid to the creator it doesn't
+        let refundable_tax = match recipient {
+            SniperTaxRecipient::Treasury => sniper_tax,
+            SniperTaxRecipient::Creator => 0,
+        };
+        assert_eq!(
+            curve.curve_sol_balance,
+            TREASURY_BALANCE - CREATION_FEE + curve_sol + refundable_tax
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_refunds_return_the_sniper_tax_kept_in_the_treasury() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let deadline = upcoming_launch();
+    let mut context = holder_program_test(&keys, &holder, holder_token_account, 0, |curve| {
+        curve.creation_timestamp = upcoming_launch();
+        curve.graduation_threshold = u64::MAX;
+        curve.graduation_deadline = Some(deadline);
+    })
+    .start_with_context()
+    .await;
+
+    let sol_amount = LAMPORTS_PER_SOL / 2;
+    let mut transaction = Transaction::new_with_payer(
+        &[buy_instruction(&keys, &holder.pubkey(), &holder_token_account, sol_amount)],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &holder], context.last_blockhash);
+    context.banks_client.process_transaction(transaction).await.unwrap();
+
+    // The curve misses its deadline, and the holder's refund includes a
+    // share of the tax they paid
+    let mut clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    clock.unix_timestamp = deadline + 1;
+    context.set_sysvar(&clock);
+    let curve = bonding_curve(&mut context.banks_client, keys.bonding_curve).await;
+    let sniper_tax = sol_amount * 4900 / 10000;
+    assert_eq!(
+        curve.curve_sol_balance,
+        TREASURY_BALANCE - CREATION_FEE + sol_amount - sol_amount / 100
+    );
+    let holding = token_balance(&mut context.banks_client, holder_token_account).await;
+    let refund = calculate_refund_amount(holding, &curve).unwrap();
+    let refund_without_tax = (curve.curve_sol_balance - sniper_tax) as u128 * holding as u128
+        / curve.total_supply as u128;
+    assert!(refund as u128 > refund_without_tax);
+
+    let holder_balance = context.banks_client.get_balance(holder.pubkey()).await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_refund_instruction(&keys, &holder.pubkey(), &holder_token_account, holding)],
+        Some(&context.payer.pubkey()),
+    );
+    transaction.sign(&[&context.payer, &holder], context.last_blockhash);
+    context.banks_client.process_transaction(transaction).await.unwrap();
+    assert_eq!(
+        context.banks_client.get_balance(holder.pubkey()).await.unwrap(),
+        holder_balance + refund
+    );
+}
+
+#[tokio::test]
+async fn test_price_impact_ignores_the_sniper_tax() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, 0, |curve| {
+            curve.creation_timestamp = upcoming_launch();
+            curve.graduation_threshold = u64::MAX;
+            curve.max_price_impact_bps = 200;
+        })
+        .start()
+        .await;
+
+    // Half of a launch buy goes to fees and the sniper tax; the half left
+    // moves the price within the limit the whole amount would break
+    let sol_amount = LAMPORTS_PER_SOL / 2;
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    let sol_after_fee = sol_amount - sol_amount / 100 - sol_amount * 4900 / 10000;
+    assert!(calculate_price_impact(sol_after_fee, &curve).unwrap() <= 200);
+    assert!(calculate_price_impact(sol_amount, &curve).unwrap() > 200);
+    let quote = calculate_buy_quote(sol_amount, &curve, curve.creation_timestamp).unwrap();
+    assert_eq!(quote.price_impact_bps, calculate_price_impact(sol_after_fee, &curve).unwrap());
+
+    let mut transaction = Transaction::new_with_payer(
+        &[buy_instruction(&keys, &holder.pubkey(), &holder_token_account, sol_amount)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert_eq!(token_balance(&mut banks_client, holder_token_account).await, quote.token_amount);
+}
+
+#[tokio::test]
+async fn test_protection_period_caps_each_wallet() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, 0, |curve| {
+            curve.creation_timestamp = upcoming_launch();
+            curve.graduation_threshold = u64::MAX;
+            curve.transaction_cooldown = 0;
+            curve.max_wallet_buy_during_protection = LAMPORTS_PER_SOL / 2;
+            curve.max_wallet_holding_bps = 0;
+        })
+        .start()
+        .await;
+
+    // Each buy is within the per-buy limit, but together they pass the wallet's total
+    let sol_amount = LAMPORTS_PER_SOL * 3 / 10;
+    let mut results = vec![];
+    for sol_amount in [sol_amount, sol_amount + 1] {
+        let mut transaction = Transaction::new_with_payer(
+            &[buy_instruction(&keys, &holder.pubkey(), &holder_token_account, sol_amount)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &holder], recent_blockhash);
+        results.push(banks_client.process_transaction(transaction).await);
+    }
+    assert!(results[0].is_ok());
+    assert_eq!(
+        results.pop().unwrap().unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(TokenCreatorError::ExceedsWalletBuyLimit.into())
+        )
+    );
+
+    let tracker = user_tracker(&mut banks_client, &keys, &holder.pubkey()).await;
+    assert_eq!(tracker.total_bought_sol, sol_amount);
+
+    // A wallet that already bought 2% of max_supply can't buy more until the
+    // period ends, even with those tokens moved out of its token account
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let mut program_test = holder_program_test(&keys, &holder, holder_token_account, 0, |curve| {
+        curve.creation_timestamp = upcoming_launch();
+    });
+    let tracker = UserTracker {
+        wallet: holder.pubkey(),
+        mint: keys.mint,
+        last_transaction_time: 0,
+        total_bought_sol: 0,
+        transaction_count: 1,
+        bump: Pubkey::find_program_address(
+            &[b"user_tracker", keys.mint.as_ref(), holder.pubkey().as_ref()],
+            &degenie_token_creator::ID,
+        )
+        .1,
+        presale_bought_sol: 0,
+        tokens_bought: 1_000_000 * 200 / 10000,
+    };
+    program_test.add_account(
+        user_tracker_address(&keys, &holder.pubkey()),
+        program_account(&tracker, 8 + UserTracker::INIT_SPACE),
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let mut transaction = Transaction::new_with_payer(
+        &[buy_instruction(&keys, &holder.pubkey(), &holder_token_account, LAMPORTS_PER_SOL / 100)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(TokenCreatorError::ExceedsWalletHoldingLimit.into())
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_presale_admits_allowlisted_wallets_up_to_their_allocation() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    // A one-wallet allowlist: the root is the holder's leaf and the proof is empty
+    let allocation = LAMPORTS_PER_SOL / 2;
+    let merkle_root = presale_leaf(&holder.pubkey(), allocation);
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, 0, |curve| {
+            curve.graduation_threshold = u64::MAX;
+            curve.transaction_cooldown = 0;
+            curve.presale_merkle_root = Some(merkle_root);
+            curve.presale_end = upcoming_launch();
+        })
+        .start()
+        .await;
+
+    let holder_key = holder.pubkey();
+    let sol_amount = LAMPORTS_PER_SOL * 3 / 10;
+    let presale_buy = |sol_amount, allocation| {
+        let proof = PresaleProof { allocation, proof: vec![] };
+        presale_buy_instruction(&keys, &holder_key, &holder_token_account, sol_amount, proof)
+    };
+    let mut results = vec![];
+    for instruction in [
+        // No proof, or a proof for a bigger allocation than the one listed
+        buy_instruction(&keys, &holder_key, &holder_token_account, sol_amount),
+        presale_buy(sol_amount, 2 * allocation),
+        // Within the allocation, then past it
+        presale_buy(sol_amount, allocation),
+        presale_buy(sol_amount + 1, allocation),
+    ] {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &holder], recent_blockhash);
+        results.push(banks_client.process_transaction(transaction).await);
+    }
+
+    let expected = [
+        Some(TokenCreatorError::NotOnPresaleAllowlist),
+        Some(TokenCreatorError::NotOnPresaleAllowlist),
+        None,
+        Some(TokenCreatorError::ExceedsPresaleAllocation),
+    ];
+    for (result, expected) in results.into_iter().zip(expected) {
+        match expected {
+            Some(error) => assert_eq!(
+                result.unwrap_err().unwrap(),
+                TransactionError::InstructionError(0, InstructionError::Custom(error.into()))
+            ),
+            None => result.unwrap(),
+        }
+    }
+
+    // Once the presale ends the curve is open to everyone
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, 0, |curve| {
+            curve.graduation_threshold = u64::MAX;
+            curve.presale_merkle_root = Some([1; 32]);
+            curve.presale_end = 1;
+        })
+        .start()
+        .await;
+    let mut transaction = Transaction::new_with_payer(
+        &[buy_instruction(&keys, &holder.pubkey(), &holder_token_account, sol_amount)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_presale_allocations_replace_the_protection_caps() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let allocation = LAMPORTS_PER_SOL / 2;
+    let merkle_root = presale_leaf(&holder.pubkey(), allocation);
+    // Launch protection allows a fifth of the allocation per buy and per wallet
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, 0, |curve| {
+            curve.creation_timestamp = upcoming_launch();
+            curve.graduation_threshold = u64::MAX;
+            curve.max_buy_during_protection = allocation / 5;
+            curve.max_wallet_buy_during_protection = allocation / 5;
+            curve.presale_merkle_root = Some(merkle_root);
+            curve.presale_end = upcoming_launch();
+        })
+        .start()
+        .await;
+
+    // The whole allocation is still usable in one buy
+    let proof = PresaleProof { allocation, proof: vec![] };
+    let buy =
+        presale_buy_instruction(&keys, &holder.pubkey(), &holder_token_account, allocation, proof);
+    let mut transaction = Transaction::new_with_payer(&[buy], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let tracker = user_tracker(&mut banks_client, &keys, &holder.pubkey()).await;
+    assert_eq!(tracker.presale_bought_sol, allocation);
+}
+
+fn user_tracker_address(keys: &PoolAccounts, wallet: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"user_tracker", keys.mint.as_ref(), wallet.as_ref()],
+        &degenie_token_creator::ID,
+    )
+    .0
+}
+
+async fn user_tracker(
+    banks_client: &mut BanksClient,
+    keys: &PoolAccounts,
+    wallet: &Pubkey,
+) -> UserTracker {
+    let address = user_tracker_address(keys, wallet);
+    let account = banks_client.get_account(address).await.unwrap().unwrap();
+    UserTracker::try_deserialize(&mut account.data.as_ref()).unwrap()
+}
+
+fn auction_commit_address(keys: &PoolAccounts, buyer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"auction_commit", keys.mint.as_ref(), buyer.as_ref()],
+        &degenie_token_creator::ID,
+    )
+    .0
+}
+
+fn commit_buy_instruction(keys: &PoolAccounts, buyer: &Pubkey, sol_amount: u64) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::CommitBuy {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            auction_commit: auction_commit_address(keys, buyer),
+            user_tracker: user_tracker_address(keys, buyer),
+            auction_escrow: keys.auction_escrow,
+            buyer: *buyer,
+            config: keys.config,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::CommitBuy { sol_amount }.data(),
+    }
+}
+
+fn settle_opening_auction_instruction(keys: &PoolAccounts, keeper: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::SettleOpeningAuction {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            treasury: keys.treasury,
+            auction_escrow: Some(keys.auction_escrow),
+            config: keys.config,
+            keeper: *keeper,
+        }
+        .to_account_metas(None),
+        data: instruction::SettleOpeningAuction {}.data(),
+    }
+}
+
+fn claim_auction_fill_instruction(
+    keys: &PoolAccounts,
+    buyer: &Pubkey,
+    buyer_token_account: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: degenie_token_creator::ID,
+        accounts: accounts::ClaimAuctionFill {
+            bonding_curve: keys.bonding_curve,
+            mint: keys.mint,
+            auction_commit: auction_commit_address(keys, buyer),
+            auction_escrow: keys.auction_escrow,
+            user_tracker: user_tracker_address(keys, buyer),
+            buyer: *buyer,
+            buyer_token_account: *buyer_token_account,
+            token_program: spl_token::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::ClaimAuctionFill {}.data(),
+    }
+}
+
+#[tokio::test]
+async fn test_opening_auction_takes_commits_instead_of_buys() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, 0, |curve| {
+            curve.opening_auction_end = upcoming_launch();
+        })
+        .start()
+        .await;
+
+    let sol_amount = LAMPORTS_PER_SOL * 3 / 10;
+    let mut transaction = Transaction::new_with_payer(
+        &[commit_buy_instruction(&keys, &holder.pubkey(), sol_amount)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let account = banks_client
+        .get_account(auction_commit_address(&keys, &holder.pubkey()))
+        .await
+        .unwrap()
+        .unwrap();
+    let commit = AuctionCommit::try_deserialize(&mut account.data.as_ref()).unwrap();
+    assert_eq!(commit.sol_amount, sol_amount);
+    let escrow = banks_client.get_account(keys.auction_escrow).await.unwrap().unwrap();
+    let rent = Rent::default().minimum_balance(8 + AuctionEscrow::INIT_SPACE);
+    assert_eq!(escrow.lamports, rent + sol_amount);
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert_eq!(curve.auction_committed_sol, sol_amount);
+    let tracker = user_tracker(&mut banks_client, &keys, &holder.pubkey()).await;
+    assert_eq!(tracker.total_bought_sol, sol_amount);
+
+    // Nobody can buy ahead of the auction, and it can't be settled early
+    let mut transaction = Transaction::new_with_payer(
+        &[buy_instruction(&keys, &holder.pubkey(), &holder_token_account, sol_amount)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(TokenCreatorError::OpeningAuctionPending.into())
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[settle_opening_auction_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client.process_transaction(transaction).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(TokenCreatorError::OpeningAuctionNotEnded.into())
+        )
+    );
+}
+
+/// A holder_program_test curve whose opening auction is over, with `commits`
+/// of (buyer, lamports) waiting in the escrow to be filled
+fn ended_auction_program_test(
+    keys: &PoolAccounts,
+    holder: &Keypair,
+    holder_token_account: Pubkey,
+    commits: &[(Pubkey, u64)],
+    configure: impl FnOnce(&mut BondingCurve),
+) -> ProgramTest {
+    let committed_sol = commits.iter().map(|(_, sol_amount)| sol_amount).sum();
+    let mut program_test = holder_program_test(keys, holder, holder_token_account, 0, |curve| {
+        curve.opening_auction_end = 1;
+        curve.auction_committed_sol = committed_sol;
+        curve.graduation_threshold = u64::MAX;
+        configure(curve);
+    });
+    let bump = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &degenie_token_creator::ID).1;
+    let escrow = AuctionEscrow {
+        mint: keys.mint,
+        bump: bump(&[b"auction_escrow", keys.mint.as_ref()]),
+    };
+    let mut escrow_account = program_account(&escrow, 8 + AuctionEscrow::INIT_SPACE);
+    escrow_account.lamports += committed_sol;
+    program_test.add_account(keys.auction_escrow, escrow_account);
+    for &(buyer, sol_amount) in commits {
+        let commit = AuctionCommit {
+            mint: keys.mint,
+            buyer,
+            sol_amount,
+            bump: bump(&[b"auction_commit", keys.mint.as_ref(), buyer.as_ref()]),
+        };
+        program_test.add_account(
+            auction_commit_address(keys, &buyer),
+            program_account(&commit, 8 + AuctionCommit::INIT_SPACE),
+        );
+        let tracker = UserTracker {
+            wallet: buyer,
+            mint: keys.mint,
+            last_transaction_time: 0,
+            total_bought_sol: sol_amount,
+            transaction_count: 0,
+            bump: bump(&[b"user_tracker", keys.mint.as_ref(), buyer.as_ref()]),
+            presale_bought_sol: 0,
+            tokens_bought: 0,
+        };
+        program_test.add_account(
+            user_tracker_address(keys, &buyer),
+            program_account(&tracker, 8 + UserTracker::INIT_SPACE),
+        );
+    }
+    program_test
+}
+
+#[tokio::test]
+async fn test_settled_auction_fills_every_commit_at_one_price() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let other_buyer = Pubkey::new_unique();
+    let holder_sol = LAMPORTS_PER_SOL / 10;
+    let other_sol = 2 * holder_sol;
+
+    // The auction is over, with two commits waiting to be filled
+    let commits = [(holder.pubkey(), holder_sol), (other_buyer, other_sol)];
+    let (mut banks_client, payer, recent_blockhash) =
+        ended_auction_program_test(&keys, &holder, holder_token_account, &commits, |_| {})
+            .start()
+            .await;
+
+    let before = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    let (filled_sol, transaction_fee, tokens) =
+        calculate_auction_fill(holder_sol + other_sol, &before).unwrap();
+    assert_eq!(filled_sol, holder_sol + other_sol);
+
+    let mut transaction = Transaction::new_with_payer(
+        &[settle_opening_auction_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // Booked as a single buy of everything committed
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert!(curve.auction_settled);
+    assert_eq!(curve.auction_token_amount, tokens);
+    assert_eq!(curve.total_supply, before.total_supply + tokens);
+    assert_eq!(curve.treasury_balance, TREASURY_BALANCE + filled_sol - transaction_fee);
+    assert_eq!(
+        curve.curve_sol_balance,
+        TREASURY_BALANCE - CREATION_FEE + filled_sol - transaction_fee
+    );
+    assert_eq!(curve.creator_fees_owed, transaction_fee / 2);
+
+    // The holder gets a third of the tokens for a third of the SOL
+    let holder_balance = banks_client.get_balance(holder.pubkey()).await.unwrap();
+    let (holder_tokens, refund_amount) = calculate_auction_share(holder_sol, &curve).unwrap();
+    assert_eq!(holder_tokens, tokens / 3);
+    assert_eq!(refund_amount, 0);
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_auction_fill_instruction(&keys, &holder.pubkey(), &holder_token_account)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(token_balance(&mut banks_client, holder_token_account).await, holder_tokens);
+    let tracker = user_tracker(&mut banks_client, &keys, &holder.pubkey()).await;
+    assert_eq!(tracker.tokens_bought, holder_tokens);
+    let commit_rent = Rent::default().minimum_balance(8 + AuctionCommit::INIT_SPACE);
+    assert_eq!(
+        banks_client.get_balance(holder.pubkey()).await.unwrap(),
+        holder_balance + commit_rent
+    );
+    let commit = auction_commit_address(&keys, &holder.pubkey());
+    assert!(banks_client.get_account(commit).await.unwrap().is_none());
+
+    // Trading is open now that the auction is settled
+    let mut transaction = Transaction::new_with_payer(
+        &[buy_instruction(&keys, &holder.pubkey(), &holder_token_account, holder_sol)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_auction_fills_are_exempt_from_the_price_impact_limit() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let commits = [(holder.pubkey(), LAMPORTS_PER_SOL), (Pubkey::new_unique(), LAMPORTS_PER_SOL)];
+    let (mut banks_client, payer, recent_blockhash) =
+        ended_auction_program_test(&keys, &holder, holder_token_account, &commits, |_| {})
+            .start()
+            .await;
+
+    // As one buy the commits would move the price far past the 5% limit
+    let before = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    let (filled_sol, transaction_fee, tokens) =
+        calculate_auction_fill(2 * LAMPORTS_PER_SOL, &before).unwrap();
+    let price_impact = calculate_price_impact(filled_sol - transaction_fee, &before).unwrap();
+    assert!(price_impact > before.max_price_impact_bps);
+
+    let mut transaction = Transaction::new_with_payer(
+        &[settle_opening_auction_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert_eq!(curve.auction_filled_sol, filled_sol);
+    assert_eq!(curve.total_supply, before.total_supply + tokens);
+}
+
+#[tokio::test]
+async fn test_auction_commits_count_toward_the_protection_caps() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let holder_sol = LAMPORTS_PER_SOL * 3 / 10;
+    let commits = [(holder.pubkey(), holder_sol)];
+    let (mut banks_client, payer, recent_blockhash) =
+        ended_auction_program_test(&keys, &holder, holder_token_account, &commits, |curve| {
+            curve.creation_timestamp = upcoming_launch();
+            curve.transaction_cooldown = 0;
+            curve.max_wallet_buy_during_protection = LAMPORTS_PER_SOL / 2;
+            curve.max_wallet_holding_bps = 0;
+        })
+        .start()
+        .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            settle_opening_auction_instruction(&keys, &payer.pubkey()),
+            claim_auction_fill_instruction(&keys, &holder.pubkey(), &holder_token_account),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The commit used 0.3 of the wallet's 0.5 SOL, so only 0.2 more can be bought
+    let remaining = LAMPORTS_PER_SOL / 2 - holder_sol;
+    let mut results = vec![];
+    for sol_amount in [remaining + 1, remaining] {
+        let mut transaction = Transaction::new_with_payer(
+            &[buy_instruction(&keys, &holder.pubkey(), &holder_token_account, sol_amount)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &holder], recent_blockhash);
+        results.push(banks_client.process_transaction(transaction).await);
+    }
+    assert_eq!(
+        results.remove(0).unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(TokenCreatorError::ExceedsWalletBuyLimit.into())
+        )
+    );
+    assert!(results[0].is_ok());
+    let tracker = user_tracker(&mut banks_client, &keys, &holder.pubkey()).await;
+    assert_eq!(tracker.total_bought_sol, LAMPORTS_PER_SOL / 2);
+    assert_eq!(tracker.tokens_bought, token_balance(&mut banks_client, holder_token_account).await);
+}
+
+#[tokio::test]
+async fn test_missed_deadline_returns_unsettled_auction_commits() {
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let holder = Keypair::new();
+    let holder_token_account = Pubkey::new_unique();
+    let holder_sol = LAMPORTS_PER_SOL / 10;
+
+    // Nobody settled the auction before the graduation deadline passed
+    let commits = [(holder.pubkey(), holder_sol)];
+    let (mut banks_client, payer, recent_blockhash) =
+        ended_auction_program_test(&keys, &holder, holder_token_account, &commits, |curve| {
+            curve.graduation_deadline = Some(1);
+        })
+        .start()
+        .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[settle_opening_auction_instruction(&keys, &payer.pubkey())],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_custom_error(
+        banks_client.process_transaction(transaction).await.unwrap_err(),
+        TokenCreatorError::RefundModeActive,
+    );
+
+    // The whole commit comes back, with no tokens
+    let holder_balance = banks_client.get_balance(holder.pubkey()).await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[claim_auction_fill_instruction(&keys, &holder.pubkey(), &holder_token_account)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(token_balance(&mut banks_client, holder_token_account).await, 0);
+    let commit_rent = Rent::default().minimum_balance(8 + AuctionCommit::INIT_SPACE);
+    assert_eq!(
+        banks_client.get_balance(holder.pubkey()).await.unwrap(),
+        holder_balance + holder_sol + commit_rent
+    );
+
+    // A curve already in refund mode takes no commits either
+    let keys = PoolAccounts::new(Pubkey::new_unique());
+    let (mut banks_client, payer, recent_blockhash) =
+        holder_program_test(&keys, &holder, holder_token_account, 0, |curve| {
+            curve.opening_auction_end = upcoming_launch();
+            curve.graduation_deadline = Some(1);
+        })
+        .start()
+        .await;
+    let mut transaction = Transaction::new_with_payer(
+        &[commit_buy_instruction(&keys, &holder.pubkey(), holder_sol)],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    assert_custom_error(
+        banks_client.process_transaction(transaction).await.unwrap_err(),
+        TokenCreatorError::RefundModeActive,
+    );
+}
+
+#[tokio::test]
+async fn test_graduation_deadline_falls_after_the_opening_auction() {
+    let creator = Keypair::new();
+    let mut keys = PoolAccounts::new(Pubkey::new_unique());
+    keys.creator = creator.pubkey();
+    let launch = upcoming_launch();
+    let (mut banks_client, payer, recent_blockhash) = pool_program_test(&keys, |curve| {
+        curve.total_supply = 0;
+        curve.creation_timestamp = launch;
+        curve.graduation_deadline = Some(launch + 30);
+    })
+    .start()
+    .await;
+
+    // An auction past the deadline is rejected, and so is a deadline moved
+    // back before the auction's end
+    for (instruction, accepted) in [
+        (set_opening_auction_instruction(&keys, 60), false),
+        (set_opening_auction_instruction(&keys, 10), true),
+        (set_graduation_deadline_instruction(&keys, Some(launch + 10)), false),
+        (set_graduation_deadline_instruction(&keys, Some(launch + 20)), true),
+    ] {
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &creator], recent_blockhash);
+        let result = banks_client.process_transaction(transaction).await;
+        if accepted {
+            result.unwrap();
+        } else {
+            assert_custom_error(result.unwrap_err(), TokenCreatorError::InvalidConfig);
+        }
+    }
+
+    let curve = bonding_curve(&mut banks_client, keys.bonding_curve).await;
+    assert_eq!(curve.opening_auction_end, launch + 10);
+    assert_eq!(curve.graduation_deadline, Some(launch + 20));
+}